@@ -0,0 +1,2 @@
+ALTER TABLE bad_words DROP COLUMN IF EXISTS version;
+ALTER TABLE regex_rules DROP COLUMN IF EXISTS version;
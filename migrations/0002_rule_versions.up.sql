@@ -0,0 +1,2 @@
+ALTER TABLE bad_words ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE regex_rules ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS rule_history;
+
+DROP TYPE IF EXISTS rule_operation_enum;
+DROP TYPE IF EXISTS rule_kind_enum;
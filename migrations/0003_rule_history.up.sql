@@ -0,0 +1,16 @@
+CREATE TYPE rule_kind_enum AS ENUM ('BAD_WORD', 'REGEX', 'SETTING');
+CREATE TYPE rule_operation_enum AS ENUM ('CREATE', 'UPDATE', 'DELETE', 'ROLLBACK');
+
+CREATE TABLE rule_history (
+    id BIGSERIAL PRIMARY KEY,
+    rule_kind rule_kind_enum NOT NULL,
+    -- id for bad words and regex rules, key for settings
+    rule_key TEXT NOT NULL,
+    operation rule_operation_enum NOT NULL,
+    actor TEXT NOT NULL,
+    before JSONB,
+    after JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX rule_history_rule_idx ON rule_history (rule_kind, rule_key, id);
@@ -0,0 +1,9 @@
+ALTER TABLE bad_words
+    DROP CONSTRAINT IF EXISTS bad_words_validity_check,
+    DROP COLUMN IF EXISTS valid_from,
+    DROP COLUMN IF EXISTS valid_until;
+
+ALTER TABLE regex_rules
+    DROP CONSTRAINT IF EXISTS regex_rules_validity_check,
+    DROP COLUMN IF EXISTS valid_from,
+    DROP COLUMN IF EXISTS valid_until;
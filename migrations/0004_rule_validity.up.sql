@@ -0,0 +1,9 @@
+ALTER TABLE bad_words
+    ADD COLUMN valid_from TIMESTAMPTZ,
+    ADD COLUMN valid_until TIMESTAMPTZ,
+    ADD CONSTRAINT bad_words_validity_check CHECK (valid_from < valid_until);
+
+ALTER TABLE regex_rules
+    ADD COLUMN valid_from TIMESTAMPTZ,
+    ADD COLUMN valid_until TIMESTAMPTZ,
+    ADD CONSTRAINT regex_rules_validity_check CHECK (valid_from < valid_until);
@@ -0,0 +1,4 @@
+ALTER TABLE bad_words DROP COLUMN IF EXISTS enabled, DROP COLUMN IF EXISTS mode;
+ALTER TABLE regex_rules DROP COLUMN IF EXISTS enabled, DROP COLUMN IF EXISTS mode;
+
+DROP TYPE IF EXISTS rule_mode_enum;
@@ -0,0 +1,9 @@
+CREATE TYPE rule_mode_enum AS ENUM ('ENFORCE', 'SHADOW');
+
+ALTER TABLE bad_words
+    ADD COLUMN enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN mode rule_mode_enum NOT NULL DEFAULT 'ENFORCE';
+
+ALTER TABLE regex_rules
+    ADD COLUMN enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN mode rule_mode_enum NOT NULL DEFAULT 'ENFORCE';
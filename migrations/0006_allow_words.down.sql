@@ -0,0 +1,4 @@
+-- Postgres can't drop a single enum value, ALLOW_WORD stays in rule_kind_enum
+DELETE FROM rule_history WHERE rule_kind = 'ALLOW_WORD';
+
+DROP TABLE IF EXISTS allow_words;
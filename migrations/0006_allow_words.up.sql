@@ -0,0 +1,7 @@
+ALTER TYPE rule_kind_enum ADD VALUE 'ALLOW_WORD';
+
+CREATE TABLE allow_words (
+    id SERIAL PRIMARY KEY,
+    word TEXT UNIQUE NOT NULL,
+    version INTEGER NOT NULL DEFAULT 1
+);
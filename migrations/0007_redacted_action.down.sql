@@ -0,0 +1,4 @@
+-- Postgres can't drop a single enum value, fall back to review for redacting rules
+-- and leave REDACTED in moderation_action_enum
+UPDATE bad_words SET moderation_action = 'NEEDS_REVIEW' WHERE moderation_action = 'REDACTED';
+UPDATE regex_rules SET moderation_action = 'NEEDS_REVIEW' WHERE moderation_action = 'REDACTED';
@@ -0,0 +1 @@
+ALTER TYPE moderation_action_enum ADD VALUE 'REDACTED';
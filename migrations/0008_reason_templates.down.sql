@@ -0,0 +1,5 @@
+-- Postgres can't drop a single enum value, REASON_TEMPLATE stays in rule_kind_enum
+DELETE FROM rule_history WHERE rule_kind = 'REASON_TEMPLATE';
+
+DROP TABLE IF EXISTS reason_templates;
+DROP TYPE IF EXISTS reason_key_enum;
@@ -0,0 +1,20 @@
+ALTER TYPE rule_kind_enum ADD VALUE 'REASON_TEMPLATE';
+
+CREATE TYPE reason_key_enum AS ENUM ('BAD_WORD', 'REGEX');
+
+CREATE TABLE reason_templates (
+    id SERIAL PRIMARY KEY,
+    -- Lowercase BCP 47 tag, e.g. tr or en-us
+    locale TEXT NOT NULL,
+    reason_key reason_key_enum NOT NULL,
+    -- {word} is replaced with the matched bad word
+    template TEXT NOT NULL,
+    version INTEGER NOT NULL DEFAULT 1,
+    UNIQUE (locale, reason_key)
+);
+
+INSERT INTO reason_templates (locale, reason_key, template) VALUES
+    ('tr', 'BAD_WORD', 'Küfür tespit edildi: {word}'),
+    ('tr', 'REGEX', 'Regex kuralı'),
+    ('en', 'BAD_WORD', 'Profanity detected: {word}'),
+    ('en', 'REGEX', 'Regex rule');
@@ -0,0 +1,7 @@
+ALTER TABLE regex_rules DROP COLUMN IF EXISTS reason_code, DROP COLUMN IF EXISTS category;
+ALTER TABLE bad_words DROP COLUMN IF EXISTS reason_code, DROP COLUMN IF EXISTS category;
+
+-- Postgres can't drop a single enum value, CATEGORY stays in rule_kind_enum
+DELETE FROM rule_history WHERE rule_kind = 'CATEGORY';
+
+DROP TABLE IF EXISTS categories;
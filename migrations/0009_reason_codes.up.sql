@@ -0,0 +1,23 @@
+ALTER TYPE rule_kind_enum ADD VALUE 'CATEGORY';
+
+CREATE TABLE categories (
+    name TEXT PRIMARY KEY CONSTRAINT categories_name_check CHECK (name ~ '^[a-z0-9_]{2,64}$'),
+    description TEXT
+);
+
+INSERT INTO categories (name, description) VALUES
+    ('profanity', 'Swearing and insults'),
+    ('pii', 'Personal data such as phone numbers and e-mail addresses'),
+    ('spam', 'Links, advertising and flooding'),
+    ('other', 'Rules without a more specific category');
+
+-- Reason codes are stable identifiers clients can switch on, e.g. PROFANITY or PII_PHONE
+ALTER TABLE bad_words
+    ADD COLUMN reason_code TEXT NOT NULL DEFAULT 'PROFANITY'
+        CONSTRAINT bad_words_reason_code_check CHECK (reason_code ~ '^[A-Z][A-Z0-9_]{1,63}$'),
+    ADD COLUMN category TEXT NOT NULL DEFAULT 'profanity' REFERENCES categories (name);
+
+ALTER TABLE regex_rules
+    ADD COLUMN reason_code TEXT NOT NULL DEFAULT 'REGEX_MATCH'
+        CONSTRAINT regex_rules_reason_code_check CHECK (reason_code ~ '^[A-Z][A-Z0-9_]{1,63}$'),
+    ADD COLUMN category TEXT NOT NULL DEFAULT 'other' REFERENCES categories (name);
@@ -0,0 +1,4 @@
+-- Postgres can't drop a single enum value, CATEGORY_THRESHOLD stays in rule_kind_enum and reason_key_enum
+DELETE FROM rule_history WHERE rule_kind = 'CATEGORY_THRESHOLD';
+
+DROP TABLE IF EXISTS category_thresholds;
@@ -0,0 +1,14 @@
+ALTER TYPE rule_kind_enum ADD VALUE 'CATEGORY_THRESHOLD';
+ALTER TYPE reason_key_enum ADD VALUE 'CATEGORY_THRESHOLD';
+
+-- A category reaching min_hits enforced hits in one comment escalates the verdict to the action
+CREATE TABLE category_thresholds (
+    id SERIAL PRIMARY KEY,
+    category TEXT NOT NULL REFERENCES categories (name) ON DELETE CASCADE,
+    min_hits INTEGER NOT NULL CONSTRAINT category_thresholds_min_hits_check CHECK (min_hits >= 1),
+    moderation_action moderation_action_enum NOT NULL
+        CONSTRAINT category_thresholds_action_check
+        CHECK (moderation_action IN ('NEEDS_REVIEW', 'REJECTED')),
+    version INTEGER NOT NULL DEFAULT 1,
+    UNIQUE (category, min_hits)
+);
@@ -0,0 +1,4 @@
+-- Separate from 0010, new enum values can't be used in the transaction that added them
+INSERT INTO reason_templates (locale, reason_key, template) VALUES
+    ('tr', 'CATEGORY_THRESHOLD', '{category} kategorisinde {hits} ihlal tespit edildi'),
+    ('en', 'CATEGORY_THRESHOLD', '{hits} {category} violations detected');
@@ -0,0 +1,3 @@
+-- Postgres can't drop a single enum value, SCORE stays in reason_key_enum
+ALTER TABLE regex_rules DROP COLUMN IF EXISTS weight;
+ALTER TABLE bad_words DROP COLUMN IF EXISTS weight;
@@ -0,0 +1,10 @@
+ALTER TYPE reason_key_enum ADD VALUE 'SCORE';
+
+-- Points added to the comment's score for every hit of the rule, 0 keeps the rule out of scoring
+ALTER TABLE bad_words
+    ADD COLUMN weight INTEGER NOT NULL DEFAULT 0
+        CONSTRAINT bad_words_weight_check CHECK (weight BETWEEN 0 AND 1000);
+
+ALTER TABLE regex_rules
+    ADD COLUMN weight INTEGER NOT NULL DEFAULT 0
+        CONSTRAINT regex_rules_weight_check CHECK (weight BETWEEN 0 AND 1000);
@@ -0,0 +1,4 @@
+-- Separate from 0012, new enum values can't be used in the transaction that added them
+INSERT INTO reason_templates (locale, reason_key, template) VALUES
+    ('tr', 'SCORE', 'Yorum puanı eşiği aştı: {score}'),
+    ('en', 'SCORE', 'Comment score exceeded the threshold: {score}');
@@ -0,0 +1,4 @@
+-- Postgres can't drop a single enum value, EXPRESSION stays in rule_kind_enum and reason_key_enum
+DELETE FROM rule_history WHERE rule_kind = 'EXPRESSION';
+
+DROP TABLE IF EXISTS rule_expressions;
@@ -0,0 +1,18 @@
+ALTER TYPE rule_kind_enum ADD VALUE 'EXPRESSION';
+ALTER TYPE reason_key_enum ADD VALUE 'EXPRESSION';
+
+-- AND/OR/NOT combinations of bad word and regex rules, evaluated on their hit counts
+CREATE TABLE rule_expressions (
+    id SERIAL PRIMARY KEY,
+    expression JSONB NOT NULL,
+    description TEXT,
+    moderation_action moderation_action_enum NOT NULL,
+    reason_code TEXT NOT NULL DEFAULT 'EXPRESSION_MATCH'
+        CONSTRAINT rule_expressions_reason_code_check CHECK (reason_code ~ '^[A-Z][A-Z0-9_]{1,63}$'),
+    category TEXT NOT NULL DEFAULT 'other' REFERENCES categories (name),
+    weight INTEGER NOT NULL DEFAULT 0
+        CONSTRAINT rule_expressions_weight_check CHECK (weight BETWEEN 0 AND 1000),
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    mode rule_mode_enum NOT NULL DEFAULT 'ENFORCE',
+    version INTEGER NOT NULL DEFAULT 1
+);
@@ -0,0 +1,4 @@
+-- Separate from 0014, new enum values can't be used in the transaction that added them
+INSERT INTO reason_templates (locale, reason_key, template) VALUES
+    ('tr', 'EXPRESSION', 'Kural kombinasyonu eşleşti'),
+    ('en', 'EXPRESSION', 'Rule combination matched');
@@ -0,0 +1,5 @@
+-- Postgres can't drop a single enum value, PII_DETECTOR and PII stay in rule_kind_enum and reason_key_enum
+DELETE FROM rule_history WHERE rule_kind = 'PII_DETECTOR';
+
+DROP TABLE IF EXISTS pii_detectors;
+DROP TYPE IF EXISTS pii_detector_enum;
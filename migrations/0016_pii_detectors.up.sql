@@ -0,0 +1,19 @@
+ALTER TYPE rule_kind_enum ADD VALUE 'PII_DETECTOR';
+ALTER TYPE reason_key_enum ADD VALUE 'PII';
+
+CREATE TYPE pii_detector_enum AS ENUM ('EMAIL', 'PHONE', 'IBAN', 'CREDIT_CARD', 'NATIONAL_ID');
+
+-- One row per built-in detector, only the action and switches can be changed
+CREATE TABLE pii_detectors (
+    id SERIAL PRIMARY KEY,
+    detector pii_detector_enum UNIQUE NOT NULL,
+    moderation_action moderation_action_enum NOT NULL DEFAULT 'REDACTED',
+    weight INTEGER NOT NULL DEFAULT 0
+        CONSTRAINT pii_detectors_weight_check CHECK (weight BETWEEN 0 AND 1000),
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    mode rule_mode_enum NOT NULL DEFAULT 'ENFORCE',
+    version INTEGER NOT NULL DEFAULT 1
+);
+
+INSERT INTO pii_detectors (detector) VALUES
+    ('EMAIL'), ('PHONE'), ('IBAN'), ('CREDIT_CARD'), ('NATIONAL_ID');
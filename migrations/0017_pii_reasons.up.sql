@@ -0,0 +1,4 @@
+-- Separate from 0016, new enum values can't be used in the transaction that added them
+INSERT INTO reason_templates (locale, reason_key, template) VALUES
+    ('tr', 'PII', 'Kişisel veri tespit edildi: {detector}'),
+    ('en', 'PII', 'Personal data detected: {detector}');
@@ -0,0 +1,5 @@
+-- Postgres can't drop a single enum value, DOMAIN_RULE and LINK stay in rule_kind_enum and reason_key_enum
+DELETE FROM rule_history WHERE rule_kind = 'DOMAIN_RULE';
+
+DROP TABLE IF EXISTS domain_rules;
+DROP TYPE IF EXISTS domain_list_enum;
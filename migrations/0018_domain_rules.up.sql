@@ -0,0 +1,23 @@
+ALTER TYPE rule_kind_enum ADD VALUE 'DOMAIN_RULE';
+ALTER TYPE reason_key_enum ADD VALUE 'LINK';
+
+CREATE TYPE domain_list_enum AS ENUM ('ALLOW', 'DENY');
+
+-- Matched on the host of every link and all of its parent domains, the most specific entry wins.
+-- Domains are stored lowercased and punycode encoded.
+CREATE TABLE domain_rules (
+    id SERIAL PRIMARY KEY,
+    domain TEXT UNIQUE NOT NULL,
+    list domain_list_enum NOT NULL,
+    -- Allowlisted domains never change the verdict
+    moderation_action moderation_action_enum,
+    reason_code TEXT NOT NULL DEFAULT 'SPAM_LINK'
+        CONSTRAINT domain_rules_reason_code_check CHECK (reason_code ~ '^[A-Z][A-Z0-9_]{1,63}$'),
+    category TEXT NOT NULL DEFAULT 'spam' REFERENCES categories (name),
+    weight INTEGER NOT NULL DEFAULT 0
+        CONSTRAINT domain_rules_weight_check CHECK (weight BETWEEN 0 AND 1000),
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    mode rule_mode_enum NOT NULL DEFAULT 'ENFORCE',
+    version INTEGER NOT NULL DEFAULT 1,
+    CONSTRAINT domain_rules_action_check CHECK ((list = 'DENY') = (moderation_action IS NOT NULL))
+);
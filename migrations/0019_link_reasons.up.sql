@@ -0,0 +1,4 @@
+-- Separate from 0018, new enum values can't be used in the transaction that added them
+INSERT INTO reason_templates (locale, reason_key, template) VALUES
+    ('tr', 'LINK', 'Yasaklı bağlantı tespit edildi: {domain}'),
+    ('en', 'LINK', 'Blocked link detected: {domain}');
@@ -0,0 +1,4 @@
+-- Separate from 0020, new enum values can't be used in the transaction that added them
+INSERT INTO reason_templates (locale, reason_key, template) VALUES
+    ('tr', 'HEURISTIC', 'Şüpheli içerik sinyali: {signal} ({value})'),
+    ('en', 'HEURISTIC', 'Suspicious content signal: {signal} ({value})');
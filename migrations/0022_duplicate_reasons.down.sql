@@ -0,0 +1,2 @@
+-- Postgres can't drop a single enum value, DUPLICATE stays in reason_key_enum
+SELECT 1;
@@ -0,0 +1,4 @@
+-- Separate from 0022, new enum values can't be used in the transaction that added them
+INSERT INTO reason_templates (locale, reason_key, template) VALUES
+    ('tr', 'DUPLICATE', 'Son yorumlardan {matches} tanesine çok benziyor'),
+    ('en', 'DUPLICATE', 'Nearly identical to {matches} recent comments');
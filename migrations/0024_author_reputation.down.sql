@@ -0,0 +1,5 @@
+-- Postgres can't drop a single enum value, REPUTATION_RULE and REPUTATION stay in rule_kind_enum and reason_key_enum
+DELETE FROM rule_history WHERE rule_kind = 'REPUTATION_RULE';
+
+DROP TABLE IF EXISTS author_reputation;
+DROP TABLE IF EXISTS reputation_rules;
@@ -0,0 +1,24 @@
+ALTER TYPE rule_kind_enum ADD VALUE 'REPUTATION_RULE';
+ALTER TYPE reason_key_enum ADD VALUE 'REPUTATION';
+
+-- An author with at least min_count verdicts of counted_action in the last window_secs gets
+-- every new comment escalated to moderation_action
+CREATE TABLE reputation_rules (
+    id SERIAL PRIMARY KEY,
+    counted_action moderation_action_enum NOT NULL,
+    min_count INTEGER NOT NULL CONSTRAINT reputation_rules_min_count_check CHECK (min_count >= 1),
+    window_secs INTEGER NOT NULL
+        CONSTRAINT reputation_rules_window_check CHECK (window_secs BETWEEN 60 AND 604800),
+    moderation_action moderation_action_enum NOT NULL
+        CONSTRAINT reputation_rules_action_check
+        CHECK (moderation_action IN ('NEEDS_REVIEW', 'REJECTED')),
+    version INTEGER NOT NULL DEFAULT 1,
+    UNIQUE (counted_action, min_count, window_secs)
+);
+
+-- Recent verdicts per author, kept in memory and written here periodically
+CREATE TABLE author_reputation (
+    author_id TEXT PRIMARY KEY,
+    verdicts JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1 @@
+DELETE FROM reason_templates WHERE reason_key = 'REPUTATION';
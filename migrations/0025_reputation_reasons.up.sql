@@ -0,0 +1,4 @@
+-- Separate from 0024, new enum values can't be used in the transaction that added them
+INSERT INTO reason_templates (locale, reason_key, template) VALUES
+    ('tr', 'REPUTATION', 'Yazarın son {window} saniyede {count} ihlali var'),
+    ('en', 'REPUTATION', 'Author had {count} violations in the last {window} seconds');
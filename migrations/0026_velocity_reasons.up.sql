@@ -0,0 +1 @@
+ALTER TYPE reason_key_enum ADD VALUE 'VELOCITY';
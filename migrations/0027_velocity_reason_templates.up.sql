@@ -0,0 +1,4 @@
+-- Separate from 0026, new enum values can't be used in the transaction that added them
+INSERT INTO reason_templates (locale, reason_key, template) VALUES
+    ('tr', 'VELOCITY', 'Son {window} saniyede çok fazla yorum: {count}'),
+    ('en', 'VELOCITY', 'Too many comments in the last {window} seconds: {count}');
@@ -0,0 +1,2 @@
+-- Postgres can't drop a single enum value, CLASSIFIER and CLASSIFIER_UNAVAILABLE stay in reason_key_enum
+SELECT 1;
@@ -0,0 +1,2 @@
+ALTER TYPE reason_key_enum ADD VALUE 'CLASSIFIER';
+ALTER TYPE reason_key_enum ADD VALUE 'CLASSIFIER_UNAVAILABLE';
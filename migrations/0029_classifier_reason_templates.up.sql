@@ -0,0 +1,6 @@
+-- Separate from 0028, new enum values can't be used in the transaction that added them
+INSERT INTO reason_templates (locale, reason_key, template) VALUES
+    ('tr', 'CLASSIFIER', 'Sınıflandırıcı {category} puanı: {score}'),
+    ('en', 'CLASSIFIER', 'Classifier {category} score: {score}'),
+    ('tr', 'CLASSIFIER_UNAVAILABLE', 'İçerik sınıflandırıcısına ulaşılamadı'),
+    ('en', 'CLASSIFIER_UNAVAILABLE', 'Content classifier unavailable');
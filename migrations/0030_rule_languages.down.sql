@@ -0,0 +1,2 @@
+ALTER TABLE regex_rules DROP COLUMN IF EXISTS language;
+ALTER TABLE bad_words DROP COLUMN IF EXISTS language;
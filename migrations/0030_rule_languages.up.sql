@@ -0,0 +1,8 @@
+-- ISO 639-3 code of the only language the rule applies to, NULL applies it to every language
+ALTER TABLE bad_words
+    ADD COLUMN language TEXT
+        CONSTRAINT bad_words_language_check CHECK (language ~ '^[a-z]{3}$');
+
+ALTER TABLE regex_rules
+    ADD COLUMN language TEXT
+        CONSTRAINT regex_rules_language_check CHECK (language ~ '^[a-z]{3}$');
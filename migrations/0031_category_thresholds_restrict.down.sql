@@ -0,0 +1,4 @@
+ALTER TABLE category_thresholds
+    DROP CONSTRAINT category_thresholds_category_fkey,
+    ADD CONSTRAINT category_thresholds_category_fkey
+        FOREIGN KEY (category) REFERENCES categories (name) ON DELETE CASCADE;
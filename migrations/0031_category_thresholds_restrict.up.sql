@@ -0,0 +1,5 @@
+-- Thresholds have their own history, deleting or restoring a category mustn't drop them silently
+ALTER TABLE category_thresholds
+    DROP CONSTRAINT category_thresholds_category_fkey,
+    ADD CONSTRAINT category_thresholds_category_fkey
+        FOREIGN KEY (category) REFERENCES categories (name) ON DELETE RESTRICT;
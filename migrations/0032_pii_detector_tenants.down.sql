@@ -0,0 +1,6 @@
+DELETE FROM pii_detectors WHERE tenant IS NOT NULL;
+
+ALTER TABLE pii_detectors
+    DROP CONSTRAINT IF EXISTS pii_detectors_tenant_detector_key,
+    DROP COLUMN IF EXISTS tenant,
+    ADD CONSTRAINT pii_detectors_detector_key UNIQUE (detector);
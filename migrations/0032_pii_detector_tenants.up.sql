@@ -0,0 +1,6 @@
+-- API key id the row applies to, NULL rows are the defaults of every key without its own row
+ALTER TABLE pii_detectors
+    ADD COLUMN tenant TEXT
+        CONSTRAINT pii_detectors_tenant_check CHECK (tenant ~ '^[a-z0-9_]{1,64}$'),
+    DROP CONSTRAINT pii_detectors_detector_key,
+    ADD CONSTRAINT pii_detectors_tenant_detector_key UNIQUE NULLS NOT DISTINCT (tenant, detector);
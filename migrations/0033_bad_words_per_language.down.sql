@@ -0,0 +1,11 @@
+-- Keeps one row per word, the all-languages one if there is one, else the oldest
+DELETE FROM bad_words b
+WHERE EXISTS (
+    SELECT 1 FROM bad_words k
+    WHERE k.word = b.word
+      AND (k.language IS NOT NULL, k.id) < (b.language IS NOT NULL, b.id)
+);
+
+ALTER TABLE bad_words
+    DROP CONSTRAINT IF EXISTS bad_words_word_language_key,
+    ADD CONSTRAINT bad_words_word_key UNIQUE (word);
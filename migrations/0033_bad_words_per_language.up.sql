@@ -0,0 +1,4 @@
+-- The same word can be listed once for every language and once for all of them
+ALTER TABLE bad_words
+    DROP CONSTRAINT bad_words_word_key,
+    ADD CONSTRAINT bad_words_word_language_key UNIQUE NULLS NOT DISTINCT (word, language);
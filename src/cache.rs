@@ -1,16 +1,96 @@
 use aho_corasick::AhoCorasick;
 use moka::future::Cache;
 use regex::{Regex, RegexSet};
-use std::sync::{Arc, RwLock};
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::Notify;
+
+use crate::{
+    duplicates::RecentComments,
+    errors::Error,
+    expressions::RuleExpr,
+    models::{
+        AllowWordRow, BadWordRow, CategoryThresholdRow, DomainList, DomainRuleRow,
+        ModerationAction, PiiDetector, PiiDetectorRow, ReasonKey, ReasonTemplateRow, RegexRuleRow,
+        ReputationRuleRow, RuleExpressionRow, RuleKind, RuleMode, SettingRow,
+    },
+    reputation::Reputation,
+    velocity::Velocity,
+};
+
+/// Disabled rules and rules outside of their validity window are kept in the database
+/// but never reach the matchers
+const ACTIVE_RULES: &str = "enabled \
+     AND (valid_from IS NULL OR valid_from <= now()) \
+     AND (valid_until IS NULL OR valid_until > now())";
+
+/// Id, word, moderation_action, mode, reason code, weight, language
+pub type CachedBadWord = (
+    i32,
+    String,
+    ModerationAction,
+    RuleMode,
+    RuleCode,
+    u32,
+    Option<String>,
+);
+
+/// Regex, description, moderation_action, mode, reason code, weight, language
+pub type CachedRegexRule = (
+    Regex,
+    Option<String>,
+    ModerationAction,
+    RuleMode,
+    RuleCode,
+    u32,
+    Option<String>,
+);
+
+/// Keyed by rule language, `None` holds the rules that apply to every language
+pub type PerLanguage<T> = BTreeMap<Option<String>, Arc<T>>;
+
+/// Machine-readable reason reported for every hit of a rule
+#[derive(Clone, Debug)]
+pub struct RuleCode {
+    pub reason_code: String,
+    pub category: String,
+}
+
+/// (min_hits, action) of every category with thresholds, highest min_hits first
+pub type CategoryThresholds = HashMap<String, Vec<(u32, ModerationAction)>>;
 
 #[derive(Clone)]
 pub struct ModerationCache {
-    pub bad_words: Cache<String, String>,
-    /// Value: Regex, description, moderation_action
-    pub regex_rules: Cache<i32, Arc<(Regex, String, String)>>,
+    /// Key: rule language, lowercased word
+    pub bad_words: Cache<(Option<String>, String), ModerationAction>,
+    pub regex_rules: Cache<i32, Arc<CachedRegexRule>>,
     pub settings: Cache<String, String>,
-    pub bad_words_matcher: Arc<RwLock<Option<Arc<BadWordsMatcher>>>>,
-    pub regex_set_bundle: Arc<RwLock<Option<Arc<RegexSetBundle>>>>,
+    /// Key: locale, reason key
+    pub reason_templates: Cache<(String, ReasonKey), String>,
+    pub bad_words_matchers: Arc<RwLock<PerLanguage<BadWordsMatcher>>>,
+    pub regex_set_bundles: Arc<RwLock<PerLanguage<RegexSetBundle>>>,
+    pub allow_words_matcher: Arc<RwLock<Option<Arc<AhoCorasick>>>>,
+    pub expression_set: Arc<RwLock<Option<Arc<ExpressionSet>>>>,
+    /// Enabled built-in personal data detectors
+    /// Every detector row by tenant, the defaults are under `None`
+    pub pii_detectors: Arc<RwLock<HashMap<Option<String>, Vec<PiiDetectorRule>>>>,
+    /// Enabled domain rules keyed by their punycode domain
+    pub domain_rules: Arc<RwLock<HashMap<String, DomainRule>>>,
+    /// Signatures of recently moderated comments for near-duplicate detection
+    pub recent_comments: RecentComments,
+    /// Recent verdicts per author and the rules that escalate repeat offenders
+    pub reputation: Reputation,
+    pub reputation_rules: Arc<RwLock<Vec<ReputationRule>>>,
+    /// Recent requests per author id and per thread id
+    pub author_velocity: Velocity,
+    pub thread_velocity: Velocity,
+    /// Woken whenever the rule tables are reloaded so the scheduler can recompute its next wake up
+    pub rules_changed: Arc<Notify>,
+    pub category_thresholds: Arc<RwLock<CategoryThresholds>>,
+    /// Would-have-matched counters of shadow rules since startup
+    pub shadow_hits: Arc<Mutex<HashMap<(RuleKind, i32), u64>>>,
 }
 
 impl ModerationCache {
@@ -19,77 +99,190 @@ impl ModerationCache {
             bad_words: Cache::builder().max_capacity(50_000).build(),
             regex_rules: Cache::builder().max_capacity(10_000).build(),
             settings: Cache::builder().max_capacity(1_000).build(),
-            bad_words_matcher: Arc::new(RwLock::new(None)),
-            regex_set_bundle: Arc::new(RwLock::new(None)),
+            reason_templates: Cache::builder().max_capacity(1_000).build(),
+            bad_words_matchers: Arc::new(RwLock::new(BTreeMap::new())),
+            regex_set_bundles: Arc::new(RwLock::new(BTreeMap::new())),
+            allow_words_matcher: Arc::new(RwLock::new(None)),
+            expression_set: Arc::new(RwLock::new(None)),
+            pii_detectors: Arc::new(RwLock::new(HashMap::new())),
+            domain_rules: Arc::new(RwLock::new(HashMap::new())),
+            recent_comments: RecentComments::new(),
+            reputation: Reputation::new(),
+            reputation_rules: Arc::new(RwLock::new(Vec::new())),
+            author_velocity: Velocity::new(),
+            thread_velocity: Velocity::new(),
+            rules_changed: Arc::new(Notify::new()),
+            category_thresholds: Arc::new(RwLock::new(HashMap::new())),
+            shadow_hits: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    // word, moderation_action
-    pub async fn load_bad_words(&self, words: Vec<(String, String)>) {
+    /// Builds one Aho-Corasick matcher per rule language
+    pub async fn load_bad_words(&self, words: Vec<CachedBadWord>) {
         debug!(
             "Loading bad words into cache | Words Loaded: {}",
             words.len()
         );
 
         self.bad_words.invalidate_all();
-        let mut patterns: Vec<String> = Vec::with_capacity(words.len());
-        let mut actions: Vec<String> = Vec::with_capacity(words.len());
-        for (word, action) in words {
-            let normalized = word.to_lowercase();
-            self.bad_words
-                .insert(normalized.clone(), action.clone())
-                .await;
-            patterns.push(normalized);
-            actions.push(action);
-        }
-
-        if patterns.is_empty() {
-            *self.bad_words_matcher.write().unwrap() = None;
-        } else {
+        let mut by_language: BTreeMap<Option<String>, Vec<CachedBadWord>> = BTreeMap::new();
+        for word in words {
+            by_language.entry(word.6.clone()).or_default().push(word);
+        }
+
+        let mut matchers = PerLanguage::new();
+        for (language, words) in by_language {
+            let mut patterns: Vec<String> = Vec::with_capacity(words.len());
+            let mut actions: Vec<ModerationAction> = Vec::with_capacity(words.len());
+            let mut ids: Vec<i32> = Vec::with_capacity(words.len());
+            let mut modes: Vec<RuleMode> = Vec::with_capacity(words.len());
+            let mut codes: Vec<RuleCode> = Vec::with_capacity(words.len());
+            let mut weights: Vec<u32> = Vec::with_capacity(words.len());
+            for (id, word, action, mode, code, weight, _) in words {
+                let normalized = word.to_lowercase();
+                self.bad_words
+                    .insert((language.clone(), normalized.clone()), action)
+                    .await;
+                patterns.push(normalized);
+                actions.push(action);
+                ids.push(id);
+                modes.push(mode);
+                codes.push(code);
+                weights.push(weight);
+            }
+
             let ac = AhoCorasick::new(patterns.iter()).expect("failed to build Aho-Corasick");
             let matcher = BadWordsMatcher {
                 ac,
                 words: patterns,
                 actions,
+                ids,
+                modes,
+                codes,
+                weights,
             };
-            *self.bad_words_matcher.write().unwrap() = Some(Arc::new(matcher));
+            matchers.insert(language, Arc::new(matcher));
         }
+
+        *self.bad_words_matchers.write().unwrap() = matchers;
     }
 
-    pub async fn load_regex_rules(&self, items: Vec<(i32, Regex, String, String)>) {
+    /// Builds one RegexSet per rule language
+    pub async fn load_regex_rules(&self, items: Vec<(i32, CachedRegexRule)>) {
         debug!(
             "Loading regex rules into cache | Rules Loaded: {}",
             items.len()
         );
 
         self.regex_rules.invalidate_all();
-        let mut patterns: Vec<String> = Vec::with_capacity(items.len());
-        let mut descriptions: Vec<String> = Vec::with_capacity(items.len());
-        let mut actions: Vec<String> = Vec::with_capacity(items.len());
-        for (id, re, desc, action) in items {
-            self.regex_rules
-                .insert(id, Arc::new((re, desc, action)))
-                .await;
+        for (id, rule) in items {
+            self.regex_rules.insert(id, Arc::new(rule)).await;
         }
 
-        for (_id, arc_val) in self.regex_rules.iter() {
-            let (re, desc, action) = &*arc_val;
-            patterns.push(re.as_str().to_string());
-            descriptions.push(desc.clone());
-            actions.push(action.clone());
+        let mut by_language: BTreeMap<Option<String>, Vec<(i32, Arc<CachedRegexRule>)>> =
+            BTreeMap::new();
+        for (id, rule) in self.regex_rules.iter() {
+            by_language
+                .entry(rule.6.clone())
+                .or_default()
+                .push((*id, rule));
         }
 
-        if patterns.is_empty() {
-            *self.regex_set_bundle.write().unwrap() = None;
-        } else {
+        let mut bundles = PerLanguage::new();
+        for (language, rules) in by_language {
+            let mut patterns: Vec<String> = Vec::with_capacity(rules.len());
+            let mut descriptions: Vec<Option<String>> = Vec::with_capacity(rules.len());
+            let mut actions: Vec<ModerationAction> = Vec::with_capacity(rules.len());
+            let mut ids: Vec<i32> = Vec::with_capacity(rules.len());
+            let mut modes: Vec<RuleMode> = Vec::with_capacity(rules.len());
+            let mut regexes: Vec<Regex> = Vec::with_capacity(rules.len());
+            let mut codes: Vec<RuleCode> = Vec::with_capacity(rules.len());
+            let mut weights: Vec<u32> = Vec::with_capacity(rules.len());
+            for (id, arc_val) in rules {
+                let (re, desc, action, mode, code, weight, _) = &*arc_val;
+                patterns.push(re.as_str().to_string());
+                regexes.push(re.clone());
+                descriptions.push(desc.clone());
+                actions.push(*action);
+                ids.push(id);
+                modes.push(*mode);
+                codes.push(code.clone());
+                weights.push(*weight);
+            }
+
             let set = RegexSet::new(&patterns).expect("failed to build RegexSet");
             let bundle = RegexSetBundle {
                 set,
+                regexes,
                 descriptions,
                 actions,
+                ids,
+                modes,
+                codes,
+                weights,
             };
-            *self.regex_set_bundle.write().unwrap() = Some(Arc::new(bundle));
+            bundles.insert(language, Arc::new(bundle));
+        }
+
+        *self.regex_set_bundles.write().unwrap() = bundles;
+    }
+
+    /// Matchers of the rules for every language and of the ones for `language`, all of them
+    /// when the language is unknown
+    pub fn bad_words_matchers_for(&self, language: Option<&str>) -> Vec<Arc<BadWordsMatcher>> {
+        for_language(&self.bad_words_matchers.read().unwrap(), language)
+    }
+
+    pub fn regex_set_bundles_for(&self, language: Option<&str>) -> Vec<Arc<RegexSetBundle>> {
+        for_language(&self.regex_set_bundles.read().unwrap(), language)
+    }
+
+    pub fn load_allow_words(&self, words: Vec<String>) {
+        debug!(
+            "Loading allow words into cache | Words Loaded: {}",
+            words.len()
+        );
+
+        if words.is_empty() {
+            *self.allow_words_matcher.write().unwrap() = None;
+        } else {
+            let patterns: Vec<String> = words.iter().map(|w| w.to_lowercase()).collect();
+            let ac = AhoCorasick::new(&patterns).expect("failed to build Aho-Corasick");
+            *self.allow_words_matcher.write().unwrap() = Some(Arc::new(ac));
+        }
+    }
+
+    pub async fn load_reason_templates(&self, items: Vec<(String, ReasonKey, String)>) {
+        debug!(
+            "Loading reason templates into cache | Templates Loaded: {}",
+            items.len()
+        );
+
+        self.reason_templates.invalidate_all();
+        for (locale, key, template) in items {
+            self.reason_templates.insert((locale, key), template).await;
+        }
+    }
+
+    // category, min_hits, moderation_action
+    pub fn load_category_thresholds(&self, items: Vec<(String, u32, ModerationAction)>) {
+        debug!(
+            "Loading category thresholds into cache | Thresholds Loaded: {}",
+            items.len()
+        );
+
+        let mut thresholds = CategoryThresholds::new();
+        for (category, min_hits, action) in items {
+            thresholds
+                .entry(category)
+                .or_default()
+                .push((min_hits, action));
+        }
+        for levels in thresholds.values_mut() {
+            levels.sort_unstable_by_key(|&(min_hits, _)| std::cmp::Reverse(min_hits));
         }
+
+        *self.category_thresholds.write().unwrap() = thresholds;
     }
 
     pub async fn load_settings(&self, items: Vec<(String, String)>) {
@@ -99,18 +292,372 @@ impl ModerationCache {
             self.settings.insert(k, v).await;
         }
     }
+
+    /// Thresholds and actions from `settings`, unset or invalid ones are ignored
+    pub async fn parsed_setting<T: FromStr>(&self, key: &str) -> Option<T> {
+        let raw = self.settings.get(key).await?;
+        match raw.trim().parse() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                warn!("Ignoring invalid setting | Setting: {} = {}", key, raw);
+                None
+            }
+        }
+    }
+
+    /// Re-reads the currently active bad words and rebuilds the Aho-Corasick matchers
+    pub async fn reload_bad_words(&self, pool: &PgPool) -> Result<(), Error> {
+        let rows: Vec<BadWordRow> = sqlx::query_as(&format!(
+            "SELECT * FROM bad_words WHERE {ACTIVE_RULES} ORDER BY id"
+        ))
+        .fetch_all(pool)
+        .await?;
+
+        self.load_bad_words(
+            rows.into_iter()
+                .map(|r| {
+                    let code = RuleCode {
+                        reason_code: r.reason_code,
+                        category: r.category,
+                    };
+                    let weight = r.weight as u32;
+                    (
+                        r.id,
+                        r.word,
+                        r.moderation_action,
+                        r.mode,
+                        code,
+                        weight,
+                        r.language,
+                    )
+                })
+                .collect(),
+        )
+        .await;
+        self.rules_changed.notify_one();
+
+        Ok(())
+    }
+
+    /// Re-reads the currently active regex rules, compiles every pattern and rebuilds the RegexSets
+    pub async fn reload_regex_rules(&self, pool: &PgPool) -> Result<(), Error> {
+        let rows: Vec<RegexRuleRow> = sqlx::query_as(&format!(
+            "SELECT * FROM regex_rules WHERE {ACTIVE_RULES} ORDER BY id"
+        ))
+        .fetch_all(pool)
+        .await?;
+
+        let mut compiled = Vec::with_capacity(rows.len());
+        for r in rows {
+            let re = Regex::new(&r.pattern).map_err(|e| Error::Regex(e.to_string()))?;
+            let code = RuleCode {
+                reason_code: r.reason_code,
+                category: r.category,
+            };
+            let weight = r.weight as u32;
+            compiled.push((
+                r.id,
+                (
+                    re,
+                    r.description,
+                    r.moderation_action,
+                    r.mode,
+                    code,
+                    weight,
+                    r.language,
+                ),
+            ));
+        }
+
+        self.load_regex_rules(compiled).await;
+        self.rules_changed.notify_one();
+
+        Ok(())
+    }
+
+    pub async fn reload_allow_words(&self, pool: &PgPool) -> Result<(), Error> {
+        let rows: Vec<AllowWordRow> = sqlx::query_as("SELECT * FROM allow_words ORDER BY id")
+            .fetch_all(pool)
+            .await?;
+
+        self.load_allow_words(rows.into_iter().map(|r| r.word).collect());
+
+        Ok(())
+    }
+
+    pub async fn reload_reason_templates(&self, pool: &PgPool) -> Result<(), Error> {
+        let rows: Vec<ReasonTemplateRow> =
+            sqlx::query_as("SELECT * FROM reason_templates ORDER BY id")
+                .fetch_all(pool)
+                .await?;
+
+        self.load_reason_templates(
+            rows.into_iter()
+                .map(|r| (r.locale, r.reason_key, r.template))
+                .collect(),
+        )
+        .await;
+
+        Ok(())
+    }
+
+    pub async fn reload_category_thresholds(&self, pool: &PgPool) -> Result<(), Error> {
+        let rows: Vec<CategoryThresholdRow> =
+            sqlx::query_as("SELECT * FROM category_thresholds ORDER BY id")
+                .fetch_all(pool)
+                .await?;
+
+        self.load_category_thresholds(
+            rows.into_iter()
+                .map(|r| (r.category, r.min_hits as u32, r.moderation_action))
+                .collect(),
+        );
+
+        Ok(())
+    }
+
+    /// Re-reads the enabled rule expressions, they were validated on write so only the JSON is parsed
+    pub async fn reload_rule_expressions(&self, pool: &PgPool) -> Result<(), Error> {
+        let rows: Vec<RuleExpressionRow> =
+            sqlx::query_as("SELECT * FROM rule_expressions WHERE enabled ORDER BY id")
+                .fetch_all(pool)
+                .await?;
+
+        debug!(
+            "Loading rule expressions into cache | Expressions Loaded: {}",
+            rows.len()
+        );
+
+        if rows.is_empty() {
+            *self.expression_set.write().unwrap() = None;
+            return Ok(());
+        }
+
+        let mut set = ExpressionSet {
+            exprs: Vec::with_capacity(rows.len()),
+            descriptions: Vec::with_capacity(rows.len()),
+            actions: Vec::with_capacity(rows.len()),
+            ids: Vec::with_capacity(rows.len()),
+            modes: Vec::with_capacity(rows.len()),
+            codes: Vec::with_capacity(rows.len()),
+            weights: Vec::with_capacity(rows.len()),
+        };
+        for r in rows {
+            let expr: RuleExpr = serde_json::from_value(r.expression)
+                .map_err(|e| Error::Validation(format!("rule expression {}: {e}", r.id)))?;
+            set.exprs.push(expr);
+            set.descriptions.push(r.description);
+            set.actions.push(r.moderation_action);
+            set.ids.push(r.id);
+            set.modes.push(r.mode);
+            set.codes.push(RuleCode {
+                reason_code: r.reason_code,
+                category: r.category,
+            });
+            set.weights.push(r.weight as u32);
+        }
+
+        *self.expression_set.write().unwrap() = Some(Arc::new(set));
+
+        Ok(())
+    }
+
+    pub async fn reload_pii_detectors(&self, pool: &PgPool) -> Result<(), Error> {
+        // Disabled rows are kept, a tenant's disabled row turns the default off for it
+        let rows: Vec<PiiDetectorRow> = sqlx::query_as("SELECT * FROM pii_detectors ORDER BY id")
+            .fetch_all(pool)
+            .await?;
+
+        debug!(
+            "Loading PII detectors into cache | Detectors Loaded: {}",
+            rows.len()
+        );
+
+        let mut by_tenant: HashMap<Option<String>, Vec<PiiDetectorRule>> = HashMap::new();
+        for r in rows {
+            by_tenant
+                .entry(r.tenant)
+                .or_default()
+                .push(PiiDetectorRule {
+                    id: r.id,
+                    detector: r.detector,
+                    action: r.moderation_action,
+                    mode: r.mode,
+                    weight: r.weight as u32,
+                    enabled: r.enabled,
+                });
+        }
+        *self.pii_detectors.write().unwrap() = by_tenant;
+
+        Ok(())
+    }
+
+    /// Enabled detectors of the tenant, its own rows take the place of the defaults
+    pub fn pii_detectors_for(&self, tenant: &str) -> Vec<PiiDetectorRule> {
+        let by_tenant = self.pii_detectors.read().unwrap();
+        let defaults = by_tenant.get(&None).map_or(&[][..], Vec::as_slice);
+        let own = by_tenant
+            .get(&Some(tenant.to_string()))
+            .map_or(&[][..], Vec::as_slice);
+
+        defaults
+            .iter()
+            .filter(|d| own.iter().all(|o| o.detector != d.detector))
+            .chain(own)
+            .filter(|r| r.enabled)
+            .cloned()
+            .collect()
+    }
+
+    pub async fn reload_domain_rules(&self, pool: &PgPool) -> Result<(), Error> {
+        let rows: Vec<DomainRuleRow> =
+            sqlx::query_as("SELECT * FROM domain_rules WHERE enabled ORDER BY id")
+                .fetch_all(pool)
+                .await?;
+
+        debug!(
+            "Loading domain rules into cache | Domains Loaded: {}",
+            rows.len()
+        );
+
+        *self.domain_rules.write().unwrap() = rows
+            .into_iter()
+            .map(|r| {
+                let rule = DomainRule {
+                    id: r.id,
+                    list: r.list,
+                    action: r.moderation_action,
+                    mode: r.mode,
+                    code: RuleCode {
+                        reason_code: r.reason_code,
+                        category: r.category,
+                    },
+                    weight: r.weight as u32,
+                };
+                (r.domain, rule)
+            })
+            .collect();
+
+        Ok(())
+    }
+
+    pub async fn reload_reputation_rules(&self, pool: &PgPool) -> Result<(), Error> {
+        let rows: Vec<ReputationRuleRow> =
+            sqlx::query_as("SELECT * FROM reputation_rules ORDER BY id")
+                .fetch_all(pool)
+                .await?;
+
+        debug!(
+            "Loading reputation rules into cache | Rules Loaded: {}",
+            rows.len()
+        );
+
+        *self.reputation_rules.write().unwrap() = rows
+            .into_iter()
+            .map(|r| ReputationRule {
+                id: r.id,
+                counted_action: r.counted_action,
+                min_count: r.min_count as usize,
+                window_secs: r.window_secs,
+                action: r.moderation_action,
+            })
+            .collect();
+
+        Ok(())
+    }
+
+    pub fn record_shadow_hit(&self, kind: RuleKind, id: i32) {
+        *self
+            .shadow_hits
+            .lock()
+            .unwrap()
+            .entry((kind, id))
+            .or_default() += 1;
+    }
+
+    pub async fn reload_settings(&self, pool: &PgPool) -> Result<(), Error> {
+        let rows: Vec<SettingRow> = sqlx::query_as("SELECT * FROM settings ORDER BY key")
+            .fetch_all(pool)
+            .await?;
+
+        self.load_settings(rows.into_iter().map(|r| (r.key, r.value)).collect())
+            .await;
+
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
 pub struct BadWordsMatcher {
     pub ac: AhoCorasick,
     pub words: Vec<String>,
-    pub actions: Vec<String>,
+    pub actions: Vec<ModerationAction>,
+    pub ids: Vec<i32>,
+    pub modes: Vec<RuleMode>,
+    pub codes: Vec<RuleCode>,
+    pub weights: Vec<u32>,
 }
 
 #[derive(Clone)]
 pub struct RegexSetBundle {
     pub set: RegexSet,
-    pub descriptions: Vec<String>,
-    pub actions: Vec<String>,
+    /// Same patterns as `set`, used to locate the matched spans
+    pub regexes: Vec<Regex>,
+    pub descriptions: Vec<Option<String>>,
+    pub actions: Vec<ModerationAction>,
+    pub ids: Vec<i32>,
+    pub modes: Vec<RuleMode>,
+    pub codes: Vec<RuleCode>,
+    pub weights: Vec<u32>,
+}
+
+fn for_language<T>(matchers: &PerLanguage<T>, language: Option<&str>) -> Vec<Arc<T>> {
+    matchers
+        .iter()
+        .filter(|(rules, _)| match (rules.as_deref(), language) {
+            (None, _) | (_, None) => true,
+            (Some(rules), Some(language)) => rules == language,
+        })
+        .map(|(_, matcher)| matcher.clone())
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct ExpressionSet {
+    pub exprs: Vec<RuleExpr>,
+    pub descriptions: Vec<Option<String>>,
+    pub actions: Vec<ModerationAction>,
+    pub ids: Vec<i32>,
+    pub modes: Vec<RuleMode>,
+    pub codes: Vec<RuleCode>,
+    pub weights: Vec<u32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PiiDetectorRule {
+    pub id: i32,
+    pub detector: PiiDetector,
+    pub action: ModerationAction,
+    pub mode: RuleMode,
+    pub weight: u32,
+    pub enabled: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct DomainRule {
+    pub id: i32,
+    pub list: DomainList,
+    pub action: Option<ModerationAction>,
+    pub mode: RuleMode,
+    pub code: RuleCode,
+    pub weight: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReputationRule {
+    pub id: i32,
+    pub counted_action: ModerationAction,
+    pub min_count: usize,
+    pub window_secs: i32,
+    pub action: ModerationAction,
 }
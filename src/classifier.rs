@@ -0,0 +1,486 @@
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::{body::Bytes, header, Request, Uri};
+use hyper_util::client::legacy::{connect::HttpConnector, Client};
+use hyper_util::rt::TokioExecutor;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{
+    cache::{ModerationCache, RuleCode},
+    detectors::{spanless_action, DetectionContext, Detector, DetectorFuture, Match},
+    models::{ModerationAction, RuleMode},
+    normalize::NormalizedText,
+    reasons::Reason,
+};
+
+const CLASSIFIER_CODE: &str = "CLASSIFIER";
+const UNAVAILABLE_CODE: &str = "CLASSIFIER_UNAVAILABLE";
+const UNAVAILABLE_CATEGORY: &str = "other";
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(300);
+/// Upper bound for `classifier_timeout_ms`, the classifier is on every comment's path
+const MAX_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_FAILURE_LIMIT: u32 = 5;
+const DEFAULT_COOLDOWN_SECS: u64 = 30;
+const MAX_RESPONSE_BYTES: usize = 64 * 1024;
+
+#[derive(Serialize)]
+struct ClassifyRequest<'a> {
+    content: &'a str,
+}
+
+/// Scores between 0 and 1 per category, e.g. `{"scores": {"toxicity": 0.93, "insult": 0.41}}`
+#[derive(Deserialize)]
+struct ClassifyResponse {
+    scores: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum ClassifyError {
+    #[error("timed out")]
+    Timeout,
+    #[error("{0}")]
+    Request(String),
+    #[error("status {0}")]
+    Status(u16),
+    #[error("invalid response: {0}")]
+    Response(String),
+}
+
+/// Skips the classifier for a cooldown once it failed `limit` times in a row, then lets a
+/// single probe through to find out whether it recovered
+#[derive(Default)]
+struct Breaker {
+    /// Consecutive failures
+    failures: u32,
+    open_until: Option<Instant>,
+    /// Start of the probe in flight after a cooldown
+    probe_started: Option<Instant>,
+}
+
+impl Breaker {
+    fn allow(&mut self, now: Instant) -> bool {
+        match self.open_until {
+            None => true,
+            Some(until) if now < until => false,
+            // A probe whose request was dropped mid-flight never reports back
+            _ if self
+                .probe_started
+                .is_some_and(|started| now.duration_since(started) < MAX_TIMEOUT) =>
+            {
+                false
+            }
+            _ => {
+                self.probe_started = Some(now);
+                true
+            }
+        }
+    }
+
+    fn succeeded(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns true when the failure opened the circuit
+    fn failed(&mut self, now: Instant, limit: u32, cooldown: Duration) -> bool {
+        self.failures += 1;
+        self.probe_started = None;
+
+        let open = self.open_until.is_some() || self.failures >= limit;
+        if open {
+            self.open_until = Some(now + cooldown);
+        }
+        open
+    }
+}
+
+/// Opt-in stage that POSTs `{"content": "..."}` to `classifier_url` and maps the returned
+/// category scores to actions through `classifier_<category>_threshold` and
+/// `classifier_<category>_action`. Plain HTTP only, e.g. a sidecar or a local mock server, the
+/// stage is skipped with a warning while `classifier_url` has another scheme such as https.
+///
+/// While the classifier times out, errors or its circuit is open, `classifier_fallback_action`
+/// decides the verdict, comments pass this stage when it's unset.
+pub struct ExternalClassifier {
+    client: Client<HttpConnector, Full<Bytes>>,
+    breaker: Mutex<Breaker>,
+    /// Last `classifier_url` skipped for its scheme, warned about once
+    skipped_url: Mutex<Option<Uri>>,
+}
+
+impl ExternalClassifier {
+    pub fn new() -> Self {
+        let mut connector = HttpConnector::new();
+        connector.set_connect_timeout(Some(MAX_TIMEOUT));
+
+        Self {
+            client: Client::builder(TokioExecutor::new()).build(connector),
+            breaker: Mutex::new(Breaker::default()),
+            skipped_url: Mutex::new(None),
+        }
+    }
+
+    async fn classify(&self, cache: &ModerationCache, content: &str) -> Vec<Match> {
+        let Some(url): Option<Uri> = cache.parsed_setting("classifier_url").await else {
+            return Vec::new();
+        };
+        if url.scheme_str() != Some("http") {
+            let mut skipped = self.skipped_url.lock().unwrap();
+            if skipped.as_ref() != Some(&url) {
+                warn!(
+                    "Ignoring classifier_url without the http scheme | Url: {}",
+                    url
+                );
+                *skipped = Some(url);
+            }
+            return Vec::new();
+        }
+
+        if !self.breaker.lock().unwrap().allow(Instant::now()) {
+            return fallback(cache).await;
+        }
+
+        let timeout = cache
+            .parsed_setting("classifier_timeout_ms")
+            .await
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_TIMEOUT)
+            .min(MAX_TIMEOUT);
+        let scores = match tokio::time::timeout(timeout, self.request(url, content)).await {
+            Ok(result) => result,
+            Err(_) => Err(ClassifyError::Timeout),
+        };
+
+        let scores = match scores {
+            Ok(scores) => {
+                self.breaker.lock().unwrap().succeeded();
+                scores
+            }
+            Err(e) => {
+                let limit = cache
+                    .parsed_setting("classifier_failure_limit")
+                    .await
+                    .unwrap_or(DEFAULT_FAILURE_LIMIT);
+                let cooldown = cache
+                    .parsed_setting("classifier_cooldown_secs")
+                    .await
+                    .unwrap_or(DEFAULT_COOLDOWN_SECS);
+                let opened = self.breaker.lock().unwrap().failed(
+                    Instant::now(),
+                    limit,
+                    Duration::from_secs(cooldown),
+                );
+
+                warn!("External classifier failed | Error: {}", e);
+                if opened {
+                    warn!(
+                        "External classifier circuit open | Cooldown Seconds: {}",
+                        cooldown
+                    );
+                }
+                return fallback(cache).await;
+            }
+        };
+
+        let mut matches = Vec::new();
+        for (category, score) in scores {
+            // Category names end up in setting keys
+            if category.is_empty()
+                || !category
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+            {
+                continue;
+            }
+
+            let Some(threshold): Option<f64> = cache
+                .parsed_setting(&format!("classifier_{category}_threshold"))
+                .await
+            else {
+                continue;
+            };
+            if score < threshold {
+                continue;
+            }
+
+            let action = spanless_action(cache, &format!("classifier_{category}_action")).await;
+            matches.push(Match {
+                rule: None,
+                action,
+                mode: RuleMode::Enforce,
+                code: RuleCode {
+                    reason_code: CLASSIFIER_CODE.to_string(),
+                    category: category.clone(),
+                },
+                reason: Reason::Classifier { category, score },
+                hits: 1,
+                weight: 0,
+                spans: Vec::new(),
+            });
+        }
+
+        matches
+    }
+
+    async fn request(
+        &self,
+        url: Uri,
+        content: &str,
+    ) -> Result<BTreeMap<String, f64>, ClassifyError> {
+        let body = serde_json::to_vec(&ClassifyRequest { content })
+            .map_err(|e| ClassifyError::Request(e.to_string()))?;
+        let request = Request::post(url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| ClassifyError::Request(e.to_string()))?;
+
+        let response = self
+            .client
+            .request(request)
+            .await
+            .map_err(|e| ClassifyError::Request(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(ClassifyError::Status(response.status().as_u16()));
+        }
+
+        let body = Limited::new(response.into_body(), MAX_RESPONSE_BYTES)
+            .collect()
+            .await
+            .map_err(|e| ClassifyError::Response(e.to_string()))?
+            .to_bytes();
+        let parsed: ClassifyResponse =
+            serde_json::from_slice(&body).map_err(|e| ClassifyError::Response(e.to_string()))?;
+
+        Ok(parsed.scores)
+    }
+}
+
+/// Verdict of a comment the classifier couldn't score, nothing when no fallback is set
+async fn fallback(cache: &ModerationCache) -> Vec<Match> {
+    let Some(action): Option<ModerationAction> =
+        cache.parsed_setting("classifier_fallback_action").await
+    else {
+        return Vec::new();
+    };
+    // There's no span to redact
+    let action = match action {
+        ModerationAction::Redacted => ModerationAction::NeedsReview,
+        action => action,
+    };
+
+    vec![Match {
+        rule: None,
+        action,
+        mode: RuleMode::Enforce,
+        code: RuleCode {
+            reason_code: UNAVAILABLE_CODE.to_string(),
+            category: UNAVAILABLE_CATEGORY.to_string(),
+        },
+        reason: Reason::ClassifierUnavailable,
+        hits: 1,
+        weight: 0,
+        spans: Vec::new(),
+    }]
+}
+
+impl Detector for ExternalClassifier {
+    fn name(&self) -> &'static str {
+        "classifier"
+    }
+
+    fn evaluate<'a>(
+        &'a self,
+        _text: &'a NormalizedText,
+        ctx: &'a DetectionContext<'a>,
+    ) -> DetectorFuture<'a> {
+        Box::pin(self.classify(ctx.cache, &ctx.req.content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const COOLDOWN: Duration = Duration::from_secs(30);
+
+    #[test]
+    fn breaker_opens_after_limit() {
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+
+        assert!(!breaker.failed(now, 3, COOLDOWN));
+        assert!(!breaker.failed(now, 3, COOLDOWN));
+        assert!(breaker.allow(now));
+        assert!(breaker.failed(now, 3, COOLDOWN));
+
+        assert!(!breaker.allow(now));
+        assert!(!breaker.allow(now + COOLDOWN - Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn breaker_lets_a_single_probe_through() {
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+        breaker.failed(now, 1, COOLDOWN);
+
+        let later = now + COOLDOWN;
+        assert!(breaker.allow(later));
+        assert!(!breaker.allow(later));
+        // A probe that never reported back is given up on after the longest timeout
+        assert!(breaker.allow(later + MAX_TIMEOUT));
+    }
+
+    #[test]
+    fn breaker_reopens_when_the_probe_fails() {
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+        breaker.failed(now, 1, COOLDOWN);
+
+        let later = now + COOLDOWN;
+        assert!(breaker.allow(later));
+        assert!(breaker.failed(later, 1, COOLDOWN));
+        assert!(!breaker.allow(later + Duration::from_secs(1)));
+        assert!(breaker.allow(later + COOLDOWN));
+    }
+
+    #[test]
+    fn breaker_closes_when_the_probe_succeeds() {
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+        breaker.failed(now, 1, COOLDOWN);
+
+        assert!(breaker.allow(now + COOLDOWN));
+        breaker.succeeded();
+        assert!(breaker.allow(now + COOLDOWN));
+        assert!(breaker.allow(now + COOLDOWN));
+        assert!(!breaker.failed(now + COOLDOWN, 2, COOLDOWN));
+    }
+
+    /// Answers every request with `body` after `delay`, counting the connections
+    async fn serve(delay: Duration, body: &'static str) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/classify", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+
+        let counter = connections.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = [0; 4096];
+                    let _ = socket.read(&mut buf).await;
+                    tokio::time::sleep(delay).await;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+
+        (url, connections)
+    }
+
+    async fn cache(settings: &[(&str, &str)]) -> ModerationCache {
+        let cache = ModerationCache::new();
+        for (key, value) in settings {
+            cache
+                .settings
+                .insert(key.to_string(), value.to_string())
+                .await;
+        }
+        cache
+    }
+
+    #[tokio::test]
+    async fn maps_scores_to_actions() {
+        let (url, _) = serve(
+            Duration::ZERO,
+            r#"{"scores": {"toxicity": 0.93, "insult": 0.41}}"#,
+        )
+        .await;
+        let cache = cache(&[
+            ("classifier_url", &url),
+            ("classifier_toxicity_threshold", "0.9"),
+            ("classifier_toxicity_action", "REJECTED"),
+            ("classifier_insult_threshold", "0.5"),
+        ])
+        .await;
+
+        let matches = ExternalClassifier::new().classify(&cache, "text").await;
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].action, ModerationAction::Rejected);
+        assert_eq!(matches[0].code.reason_code, CLASSIFIER_CODE);
+        assert_eq!(matches[0].code.category, "toxicity");
+    }
+
+    #[tokio::test]
+    async fn falls_back_on_timeout() {
+        let (url, _) = serve(Duration::from_secs(2), r#"{"scores": {}}"#).await;
+        let cache = cache(&[
+            ("classifier_url", &url),
+            ("classifier_timeout_ms", "50"),
+            ("classifier_fallback_action", "REDACTED"),
+        ])
+        .await;
+
+        let matches = ExternalClassifier::new().classify(&cache, "text").await;
+
+        assert_eq!(matches.len(), 1);
+        // Nothing to redact without a span
+        assert_eq!(matches[0].action, ModerationAction::NeedsReview);
+        assert_eq!(matches[0].code.reason_code, UNAVAILABLE_CODE);
+    }
+
+    #[tokio::test]
+    async fn passes_without_a_fallback() {
+        let (url, _) = serve(Duration::ZERO, "not json").await;
+        let cache = cache(&[("classifier_url", &url)]).await;
+
+        assert!(ExternalClassifier::new()
+            .classify(&cache, "text")
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn open_circuit_skips_the_request() {
+        let (url, connections) = serve(Duration::ZERO, "not json").await;
+        let cache = cache(&[
+            ("classifier_url", &url),
+            ("classifier_failure_limit", "2"),
+            ("classifier_fallback_action", "NEEDS_REVIEW"),
+        ])
+        .await;
+        let classifier = ExternalClassifier::new();
+
+        for _ in 0..4 {
+            let matches = classifier.classify(&cache, "text").await;
+            assert_eq!(matches[0].code.reason_code, UNAVAILABLE_CODE);
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn skips_https() {
+        let cache = cache(&[
+            ("classifier_url", "https://127.0.0.1:1/classify"),
+            ("classifier_fallback_action", "REJECTED"),
+        ])
+        .await;
+
+        assert!(ExternalClassifier::new()
+            .classify(&cache, "text")
+            .await
+            .is_empty());
+    }
+}
@@ -0,0 +1,709 @@
+use std::collections::HashMap;
+use std::future::{ready, Future};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{
+    cache::{DomainRule, ModerationCache, RuleCode},
+    classifier::ExternalClassifier,
+    duplicates, heuristics,
+    models::{CommentRequest, DomainList, ModerationAction, RuleKind, RuleMode},
+    normalize::NormalizedText,
+    pii,
+    reasons::Reason,
+    urls,
+};
+
+/// Seeded by the reason code migration, every PII detector hit is reported under it
+const PII_CATEGORY: &str = "pii";
+/// Seeded by the reason code migration as well, used for every check without a rule row
+const SPAM_CATEGORY: &str = "spam";
+const DUPLICATE_CODE: &str = "NEAR_DUPLICATE";
+const DEFAULT_DUPLICATE_SIMILARITY: u32 = 85;
+const DEFAULT_DUPLICATE_WINDOW_SECS: u64 = 60 * 60;
+const AUTHOR_VELOCITY_CODE: &str = "AUTHOR_VELOCITY";
+const THREAD_VELOCITY_CODE: &str = "THREAD_VELOCITY";
+const DEFAULT_VELOCITY_WINDOW_SECS: u64 = 60;
+
+pub type DetectorFuture<'a> = Pin<Box<dyn Future<Output = Vec<Match>> + Send + 'a>>;
+
+/// A moderation stage. Detectors only report what they found, the pipeline turns the
+/// matches into the verdict, the score and the redacted content.
+pub trait Detector: Send + Sync {
+    /// Used in the `detector_pipeline` settings
+    fn name(&self) -> &'static str;
+
+    fn evaluate<'a>(
+        &'a self,
+        text: &'a NormalizedText,
+        ctx: &'a DetectionContext<'a>,
+    ) -> DetectorFuture<'a>;
+}
+
+/// Everything about the comment besides its normalized text
+pub struct DetectionContext<'a> {
+    pub cache: &'a ModerationCache,
+    pub req: &'a CommentRequest,
+    /// Id of the API key that sent the comment
+    pub tenant: &'a str,
+    /// ISO 639-3 code of the detected language, `None` when it couldn't be told
+    pub language: Option<&'a str>,
+}
+
+pub struct Match {
+    /// Rule row behind the match, `None` for checks configured through `settings`
+    pub rule: Option<(RuleKind, i32)>,
+    pub action: ModerationAction,
+    /// Shadow matches are logged and counted but never change the verdict
+    pub mode: RuleMode,
+    pub code: RuleCode,
+    pub reason: Reason,
+    /// Occurrences, counted towards the category, the rule's hits and its points
+    pub hits: u32,
+    pub weight: u32,
+    /// Byte spans of the normalized text, masked when the verdict ends up REDACTED
+    pub spans: Vec<(usize, usize)>,
+}
+
+impl Match {
+    /// Enforced match of a check without a rule row, a span or a weight
+    fn flag(action: ModerationAction, reason_code: &str, reason: Reason) -> Self {
+        Self {
+            rule: None,
+            action,
+            mode: RuleMode::Enforce,
+            code: RuleCode {
+                reason_code: reason_code.to_string(),
+                category: SPAM_CATEGORY.to_string(),
+            },
+            reason,
+            hits: 1,
+            weight: 0,
+            spans: Vec::new(),
+        }
+    }
+}
+
+/// Detectors by name, in their default order
+pub struct Detectors {
+    registered: Vec<Arc<dyn Detector>>,
+}
+
+impl Detectors {
+    /// Bad words, regex rules, PII, links, heuristics, near-duplicates, velocity, then the
+    /// external classifier
+    pub fn builtin() -> Self {
+        let mut detectors = Self {
+            registered: Vec::new(),
+        };
+        detectors.register(Arc::new(BadWords));
+        detectors.register(Arc::new(RegexRules));
+        detectors.register(Arc::new(PiiDetectors));
+        detectors.register(Arc::new(Links));
+        detectors.register(Arc::new(Heuristics));
+        detectors.register(Arc::new(Duplicates));
+        detectors.register(Arc::new(Velocity));
+        detectors.register(Arc::new(ExternalClassifier::new()));
+        detectors
+    }
+
+    /// Appends the detector to the default order, or replaces the one with the same name
+    pub fn register(&mut self, detector: Arc<dyn Detector>) {
+        match self
+            .registered
+            .iter_mut()
+            .find(|d| d.name() == detector.name())
+        {
+            Some(existing) => *existing = detector,
+            None => self.registered.push(detector),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Detector>> {
+        self.registered.iter().find(|d| d.name() == name)
+    }
+
+    /// Comma separated detector names from `detector_pipeline_<tenant>`, then
+    /// `detector_pipeline`. Every registered detector runs when neither is set.
+    pub async fn pipeline(&self, cache: &ModerationCache, tenant: &str) -> Vec<Arc<dyn Detector>> {
+        let configured = match cache
+            .settings
+            .get(&format!("detector_pipeline_{tenant}"))
+            .await
+        {
+            Some(names) => Some(names),
+            None => cache.settings.get("detector_pipeline").await,
+        };
+
+        let Some(names) = configured else {
+            return self.registered.clone();
+        };
+
+        names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .filter_map(|name| {
+                let detector = self.get(name).cloned();
+                if detector.is_none() {
+                    warn!("Skipping unknown detector in pipeline | Detector: {}", name);
+                }
+                detector
+            })
+            .collect()
+    }
+}
+
+/// Action setting of a check that flags the whole comment, there's no span to redact
+pub async fn spanless_action(cache: &ModerationCache, key: &str) -> ModerationAction {
+    match cache.parsed_setting(key).await {
+        Some(ModerationAction::Redacted) | None => ModerationAction::NeedsReview,
+        Some(action) => action,
+    }
+}
+
+/// Aho-Corasick matchers over the active bad words of the comment's language, skipping hits
+/// inside allowlisted words
+struct BadWords;
+
+impl Detector for BadWords {
+    fn name(&self) -> &'static str {
+        "bad_words"
+    }
+
+    fn evaluate<'a>(
+        &'a self,
+        text: &'a NormalizedText,
+        ctx: &'a DetectionContext<'a>,
+    ) -> DetectorFuture<'a> {
+        let text = text.text.as_str();
+        let cache = ctx.cache;
+
+        // Bad word hits that sit entirely inside one of these spans are not real hits
+        let allowed: Vec<(usize, usize)> = cache
+            .allow_words_matcher
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|ac| {
+                ac.find_overlapping_iter(text)
+                    .map(|m| (m.start(), m.end()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut matches = Vec::new();
+        for bundle in cache.bad_words_matchers_for(ctx.language) {
+            let mut shadowed = Vec::new();
+            for mat in bundle.ac.find_overlapping_iter(text) {
+                let pat_index = mat.pattern().as_usize();
+
+                if allowed
+                    .iter()
+                    .any(|&(start, end)| start <= mat.start() && mat.end() <= end)
+                {
+                    continue;
+                }
+
+                // Shadow words are reported once however often they occur
+                let mode = bundle.modes[pat_index];
+                if mode == RuleMode::Shadow {
+                    if shadowed.contains(&pat_index) {
+                        continue;
+                    }
+                    shadowed.push(pat_index);
+                }
+
+                matches.push(Match {
+                    rule: Some((RuleKind::BadWord, bundle.ids[pat_index])),
+                    action: bundle.actions[pat_index],
+                    mode,
+                    code: bundle.codes[pat_index].clone(),
+                    reason: Reason::BadWord {
+                        word: bundle.words[pat_index].clone(),
+                    },
+                    hits: 1,
+                    weight: bundle.weights[pat_index],
+                    spans: vec![(mat.start(), mat.end())],
+                });
+            }
+        }
+
+        Box::pin(ready(matches))
+    }
+}
+
+/// RegexSets over the active regex rules of the comment's language, one match per rule with
+/// all of its occurrences
+struct RegexRules;
+
+impl Detector for RegexRules {
+    fn name(&self) -> &'static str {
+        "regex"
+    }
+
+    fn evaluate<'a>(
+        &'a self,
+        text: &'a NormalizedText,
+        ctx: &'a DetectionContext<'a>,
+    ) -> DetectorFuture<'a> {
+        let text = text.text.as_str();
+
+        let mut matches = Vec::new();
+        for bundle in ctx.cache.regex_set_bundles_for(ctx.language) {
+            for idx in bundle.set.matches(text).into_iter() {
+                let spans: Vec<(usize, usize)> = bundle.regexes[idx]
+                    .find_iter(text)
+                    .filter(|m| !m.is_empty())
+                    .map(|m| (m.start(), m.end()))
+                    .collect();
+
+                matches.push(Match {
+                    rule: Some((RuleKind::Regex, bundle.ids[idx])),
+                    action: bundle.actions[idx],
+                    mode: bundle.modes[idx],
+                    code: bundle.codes[idx].clone(),
+                    reason: Reason::Regex {
+                        description: bundle.descriptions[idx].clone(),
+                    },
+                    // Patterns that only match empty strings still count once
+                    hits: spans.len().max(1) as u32,
+                    weight: bundle.weights[idx],
+                    spans,
+                });
+            }
+        }
+
+        Box::pin(ready(matches))
+    }
+}
+
+/// Built-in personal data detectors enabled for the tenant, or by default
+struct PiiDetectors;
+
+impl Detector for PiiDetectors {
+    fn name(&self) -> &'static str {
+        "pii"
+    }
+
+    fn evaluate<'a>(
+        &'a self,
+        text: &'a NormalizedText,
+        ctx: &'a DetectionContext<'a>,
+    ) -> DetectorFuture<'a> {
+        let detectors = ctx.cache.pii_detectors_for(ctx.tenant);
+
+        let matches = detectors
+            .into_iter()
+            .filter_map(|rule| {
+                let spans = pii::detect(&text.text, rule.detector);
+                if spans.is_empty() {
+                    return None;
+                }
+
+                Some(Match {
+                    rule: Some((RuleKind::PiiDetector, rule.id)),
+                    action: rule.action,
+                    mode: rule.mode,
+                    code: RuleCode {
+                        reason_code: rule.detector.reason_code().to_string(),
+                        category: PII_CATEGORY.to_string(),
+                    },
+                    reason: Reason::Pii {
+                        detector: rule.detector,
+                    },
+                    hits: spans.len() as u32,
+                    weight: rule.weight,
+                    spans,
+                })
+            })
+            .collect();
+
+        Box::pin(ready(matches))
+    }
+}
+
+/// Links whose domain, or one of its parent domains, is denied
+struct Links;
+
+impl Detector for Links {
+    fn name(&self) -> &'static str {
+        "links"
+    }
+
+    fn evaluate<'a>(
+        &'a self,
+        text: &'a NormalizedText,
+        ctx: &'a DetectionContext<'a>,
+    ) -> DetectorFuture<'a> {
+        let links = urls::extract(&text.text);
+
+        let mut matches = Vec::new();
+        if !links.is_empty() {
+            let rules = ctx.cache.domain_rules.read().unwrap();
+            for link in links {
+                let Some(rule) = denied_by(ctx.cache, &rules, &link.host) else {
+                    continue;
+                };
+                // Enforced by the table check, DENY entries always have one
+                let Some(action) = rule.action else {
+                    continue;
+                };
+
+                matches.push(Match {
+                    rule: Some((RuleKind::DomainRule, rule.id)),
+                    action,
+                    mode: rule.mode,
+                    code: rule.code.clone(),
+                    reason: Reason::Link { domain: link.host },
+                    hits: 1,
+                    weight: rule.weight,
+                    spans: vec![(link.start, link.end)],
+                });
+            }
+        }
+
+        Box::pin(ready(matches))
+    }
+}
+
+/// Most specific deny entry of the host or one of its parent domains. An allow entry
+/// on the way ends the lookup, shadow allow entries are only counted.
+fn denied_by<'a>(
+    cache: &ModerationCache,
+    rules: &'a HashMap<String, DomainRule>,
+    host: &str,
+) -> Option<&'a DomainRule> {
+    for domain in urls::suffixes(host) {
+        let Some(rule) = rules.get(domain) else {
+            continue;
+        };
+
+        match (rule.list, rule.mode) {
+            (DomainList::Deny, _) => return Some(rule),
+            (DomainList::Allow, RuleMode::Enforce) => return None,
+            (DomainList::Allow, RuleMode::Shadow) => {
+                info!(
+                    "Shadow domain allow entry matched | Rule: {} | Domain: {}",
+                    rule.id, domain
+                );
+                cache.record_shadow_hit(RuleKind::DomainRule, rule.id);
+            }
+        }
+    }
+
+    None
+}
+
+/// Content signals, each one enabled through its `heuristic_<key>_threshold` setting
+struct Heuristics;
+
+impl Detector for Heuristics {
+    fn name(&self) -> &'static str {
+        "heuristics"
+    }
+
+    fn evaluate<'a>(
+        &'a self,
+        _text: &'a NormalizedText,
+        ctx: &'a DetectionContext<'a>,
+    ) -> DetectorFuture<'a> {
+        Box::pin(async move {
+            let cache = ctx.cache;
+
+            let mut matches = Vec::new();
+            for (signal, value) in heuristics::measure(&ctx.req.content) {
+                let key = signal.key();
+                let Some(threshold) = cache
+                    .parsed_setting(&format!("heuristic_{key}_threshold"))
+                    .await
+                else {
+                    continue;
+                };
+                if !signal.hits(value, threshold) {
+                    continue;
+                }
+
+                let action = spanless_action(cache, &format!("heuristic_{key}_action")).await;
+                matches.push(Match::flag(
+                    action,
+                    signal.reason_code(),
+                    Reason::Heuristic {
+                        signal,
+                        value,
+                        threshold,
+                    },
+                ));
+            }
+
+            matches
+        })
+    }
+}
+
+/// Opt-in, flags a comment once more than `duplicate_max_similar` recent comments of the
+/// same tenant look alike
+struct Duplicates;
+
+impl Detector for Duplicates {
+    fn name(&self) -> &'static str {
+        "duplicates"
+    }
+
+    fn evaluate<'a>(
+        &'a self,
+        _text: &'a NormalizedText,
+        ctx: &'a DetectionContext<'a>,
+    ) -> DetectorFuture<'a> {
+        Box::pin(async move {
+            let cache = ctx.cache;
+
+            let Some(max_similar) = cache.parsed_setting("duplicate_max_similar").await else {
+                return Vec::new();
+            };
+            let Some(signature) = duplicates::signature(&ctx.req.content) else {
+                return Vec::new();
+            };
+
+            let min_similarity = cache
+                .parsed_setting("duplicate_similarity")
+                .await
+                .unwrap_or(DEFAULT_DUPLICATE_SIMILARITY);
+            let window = cache
+                .parsed_setting("duplicate_window_secs")
+                .await
+                .unwrap_or(DEFAULT_DUPLICATE_WINDOW_SECS);
+            let matches = cache
+                .recent_comments
+                .count_similar_and_record(
+                    ctx.tenant,
+                    signature,
+                    Duration::from_secs(window),
+                    min_similarity,
+                )
+                .await;
+
+            if matches <= max_similar {
+                return Vec::new();
+            }
+
+            let action = spanless_action(cache, "duplicate_action").await;
+            vec![Match::flag(
+                action,
+                DUPLICATE_CODE,
+                Reason::Duplicate { matches },
+            )]
+        })
+    }
+}
+
+/// Opt-in per scope, a scope is limited once its `velocity_<scope>_limit` setting is set.
+/// Authors and threads are counted per tenant, the same ids may come from several API keys
+struct Velocity;
+
+impl Detector for Velocity {
+    fn name(&self) -> &'static str {
+        "velocity"
+    }
+
+    fn evaluate<'a>(
+        &'a self,
+        _text: &'a NormalizedText,
+        ctx: &'a DetectionContext<'a>,
+    ) -> DetectorFuture<'a> {
+        Box::pin(async move {
+            let cache = ctx.cache;
+            let req = ctx.req;
+
+            let mut matches = Vec::new();
+            for (scope, key, counters, reason_code) in [
+                (
+                    "author",
+                    req.author_id.as_deref(),
+                    &cache.author_velocity,
+                    AUTHOR_VELOCITY_CODE,
+                ),
+                (
+                    "thread",
+                    req.thread_id.as_deref(),
+                    &cache.thread_velocity,
+                    THREAD_VELOCITY_CODE,
+                ),
+            ] {
+                let Some(key) = key else {
+                    continue;
+                };
+                let Some(limit): Option<usize> = cache
+                    .parsed_setting(&format!("velocity_{scope}_limit"))
+                    .await
+                else {
+                    continue;
+                };
+                let window_secs = cache
+                    .parsed_setting(&format!("velocity_{scope}_window_secs"))
+                    .await
+                    .unwrap_or(DEFAULT_VELOCITY_WINDOW_SECS);
+
+                let count = counters
+                    .record(ctx.tenant, key, Duration::from_secs(window_secs))
+                    .await;
+                if count <= limit {
+                    continue;
+                }
+
+                let action = spanless_action(cache, &format!("velocity_{scope}_action")).await;
+                matches.push(Match::flag(
+                    action,
+                    reason_code,
+                    Reason::Velocity { count, window_secs },
+                ));
+            }
+
+            matches
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cache::PiiDetectorRule, models::PiiDetector};
+
+    fn rule(id: i32, list: DomainList, mode: RuleMode) -> DomainRule {
+        DomainRule {
+            id,
+            list,
+            action: (list == DomainList::Deny).then_some(ModerationAction::Rejected),
+            mode,
+            code: RuleCode {
+                reason_code: "LINK".to_string(),
+                category: "spam".to_string(),
+            },
+            weight: 0,
+        }
+    }
+
+    #[test]
+    fn subdomains_inherit_a_denied_parent() {
+        let cache = ModerationCache::new();
+        let rules = HashMap::from([
+            (
+                "example.com".to_string(),
+                rule(1, DomainList::Deny, RuleMode::Enforce),
+            ),
+            (
+                "docs.example.com".to_string(),
+                rule(2, DomainList::Allow, RuleMode::Enforce),
+            ),
+            (
+                "beta.example.com".to_string(),
+                rule(3, DomainList::Allow, RuleMode::Shadow),
+            ),
+            (
+                "bad.example.com".to_string(),
+                rule(4, DomainList::Deny, RuleMode::Enforce),
+            ),
+        ]);
+        let denied = |host: &str| denied_by(&cache, &rules, host).map(|r| r.id);
+
+        assert_eq!(denied("example.com"), Some(1));
+        assert_eq!(denied("a.b.example.com"), Some(1));
+        // The most specific entry wins
+        assert_eq!(denied("x.bad.example.com"), Some(4));
+        assert_eq!(denied("api.docs.example.com"), None);
+        // Shadow allow entries don't stop the lookup
+        assert_eq!(denied("beta.example.com"), Some(1));
+        assert_eq!(denied("notexample.com"), None);
+        assert_eq!(denied("example.com.tr"), None);
+    }
+
+    #[test]
+    fn obfuscated_links_hit_the_canonical_host() {
+        let cache = ModerationCache::new();
+        let rules = HashMap::from([(
+            "xn--rnek-4qa.com".to_string(),
+            rule(1, DomainList::Deny, RuleMode::Enforce),
+        )]);
+
+        for text in [
+            "shop.örnek[.]com",
+            "SHOP.XN--RNEK-4QA.COM",
+            "örnek (dot) com",
+        ] {
+            let text = NormalizedText::new(text);
+            let links = urls::extract(&text.text);
+            assert_eq!(links.len(), 1, "{}", text.text);
+            assert!(denied_by(&cache, &rules, &links[0].host).is_some());
+        }
+    }
+
+    fn pii(
+        id: i32,
+        detector: PiiDetector,
+        action: ModerationAction,
+        enabled: bool,
+    ) -> PiiDetectorRule {
+        PiiDetectorRule {
+            id,
+            detector,
+            action,
+            mode: RuleMode::Enforce,
+            weight: 0,
+            enabled,
+        }
+    }
+
+    #[tokio::test]
+    async fn pii_detectors_resolve_per_tenant() {
+        let cache = ModerationCache::new();
+        *cache.pii_detectors.write().unwrap() = HashMap::from([
+            (
+                None,
+                vec![
+                    pii(1, PiiDetector::Email, ModerationAction::Redacted, true),
+                    pii(2, PiiDetector::Phone, ModerationAction::Redacted, false),
+                ],
+            ),
+            (
+                Some("strict".to_string()),
+                vec![
+                    pii(3, PiiDetector::Email, ModerationAction::Rejected, true),
+                    pii(4, PiiDetector::Phone, ModerationAction::Rejected, true),
+                ],
+            ),
+            (
+                Some("lax".to_string()),
+                vec![pii(
+                    5,
+                    PiiDetector::Email,
+                    ModerationAction::Rejected,
+                    false,
+                )],
+            ),
+        ]);
+        let req = CommentRequest::default();
+        let text = NormalizedText::new("mail x@example.com or call 0532 123 45 67");
+
+        for (tenant, expected) in [
+            ("other", vec![1]),
+            ("strict", vec![3, 4]),
+            // A disabled row of the tenant turns the default off
+            ("lax", vec![]),
+        ] {
+            let ctx = DetectionContext {
+                cache: &cache,
+                req: &req,
+                tenant,
+                language: None,
+            };
+            let hits: Vec<i32> = PiiDetectors
+                .evaluate(&text, &ctx)
+                .await
+                .into_iter()
+                .filter_map(|m| m.rule.map(|(_, id)| id))
+                .collect();
+            assert_eq!(hits, expected, "{tenant}");
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use moka::future::Cache;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Character n-grams the signature is built from, small edits only change a few of them
+const SHINGLE_LEN: usize = 4;
+/// Shorter comments, e.g. "thanks!", are too common to be compared at all
+const MIN_SHINGLES: usize = 8;
+/// Upper bounds for the `duplicate_window_secs` setting and for the signatures kept per scope
+pub const MAX_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+const MAX_RECENT_PER_SCOPE: usize = 500;
+const MAX_SCOPES: u64 = 100_000;
+
+/// 64-bit SimHash of the comment's character shingles, `None` when it's too short.
+/// Letters and digits are kept, everything else collapses into a single space.
+pub fn signature(text: &str) -> Option<u64> {
+    let mut chars: Vec<char> = Vec::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            chars.push(c);
+        } else if chars.last().is_some_and(|&last| last != ' ') {
+            chars.push(' ');
+        }
+    }
+    if chars.last() == Some(&' ') {
+        chars.pop();
+    }
+
+    if chars.len() < SHINGLE_LEN + MIN_SHINGLES - 1 {
+        return None;
+    }
+
+    let mut weights = [0i32; 64];
+    for shingle in chars.windows(SHINGLE_LEN) {
+        let mut hasher = DefaultHasher::new();
+        shingle.hash(&mut hasher);
+        let hash = hasher.finish();
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            *weight += if hash >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    }
+
+    Some(
+        weights
+            .iter()
+            .enumerate()
+            .filter(|(_, &weight)| weight > 0)
+            .fold(0, |signature, (bit, _)| signature | 1 << bit),
+    )
+}
+
+/// Percentage of equal bits of two signatures
+pub fn similarity(a: u64, b: u64) -> u32 {
+    (64 - (a ^ b).count_ones()) * 100 / 64
+}
+
+/// (seen at, signature) of a scope's recent comments, oldest first
+type Signatures = Arc<Mutex<VecDeque<(Instant, u64)>>>;
+
+/// Signatures of recent comments per scope, e.g. per API key
+#[derive(Clone)]
+pub struct RecentComments {
+    scopes: Cache<String, Signatures>,
+}
+
+impl RecentComments {
+    pub fn new() -> Self {
+        Self {
+            scopes: Cache::builder()
+                .max_capacity(MAX_SCOPES)
+                .time_to_idle(MAX_WINDOW)
+                .build(),
+        }
+    }
+
+    /// Counts the comments of the last `window` at least `min_similarity` percent similar to
+    /// `signature`, then records it
+    pub async fn count_similar_and_record(
+        &self,
+        scope: &str,
+        signature: u64,
+        window: Duration,
+        min_similarity: u32,
+    ) -> usize {
+        let recent = self
+            .scopes
+            .get_with(scope.to_string(), async { Default::default() })
+            .await;
+        let mut recent = recent.lock().unwrap();
+
+        let now = Instant::now();
+        while recent
+            .front()
+            .is_some_and(|&(at, _)| now.duration_since(at) > window.min(MAX_WINDOW))
+        {
+            recent.pop_front();
+        }
+
+        let similar = recent
+            .iter()
+            .filter(|&&(_, other)| similarity(signature, other) >= min_similarity)
+            .count();
+
+        if recent.len() == MAX_RECENT_PER_SCOPE {
+            recent.pop_front();
+        }
+        recent.push_back((now, signature));
+
+        similar
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPAM: &str = "Check out my channel for free crypto giveaways, link in bio!";
+    /// Default of the `duplicate_similarity` setting
+    const THRESHOLD: u32 = 85;
+
+    #[test]
+    fn short_comments_have_no_signature() {
+        assert_eq!(signature("thanks!"), None);
+        assert_eq!(signature("   ...   "), None);
+        assert!(signature("thanks a lot!").is_some());
+    }
+
+    #[test]
+    fn case_and_punctuation_dont_matter() {
+        assert_eq!(
+            signature(SPAM),
+            signature("CHECK OUT my channel... for free crypto giveaways -- link in bio")
+        );
+    }
+
+    #[test]
+    fn near_duplicates_pass_the_threshold() {
+        let original = signature(SPAM).unwrap();
+        for edited in [
+            "Check out my channel for free crypto giveaways, link in bio!!!",
+            "Check out my chanel for free crypto giveaways, link in bio!",
+            "Check out my channel for free crypto giveaway, link in bio",
+            "Check out our channel for free crypto giveaways, link in bio!",
+            "check out my channel for free crypto giveaways link in bio 🔥",
+        ] {
+            let similar = similarity(original, signature(edited).unwrap());
+            assert!(similar >= THRESHOLD, "{edited}: {similar}");
+        }
+    }
+
+    #[test]
+    fn unrelated_comments_dont() {
+        let original = signature(SPAM).unwrap();
+        for other in [
+            "I think the referee made the wrong call in the second half.",
+            "Bu tarifi denedim, çok lezzetli oldu, teşekkürler!",
+            "Does anyone know when the next episode comes out?",
+        ] {
+            let similar = similarity(original, signature(other).unwrap());
+            assert!(similar < THRESHOLD, "{other}: {similar}");
+        }
+    }
+
+    #[test]
+    fn similarity_counts_equal_bits() {
+        assert_eq!(similarity(0, 0), 100);
+        assert_eq!(similarity(0, u64::MAX), 0);
+        assert_eq!(similarity(0, 0xffff_ffff), 50);
+        assert_eq!(similarity(0, 0b111), 95);
+    }
+
+    #[tokio::test]
+    async fn scopes_are_separate() {
+        let recent = RecentComments::new();
+        let window = Duration::from_secs(60);
+        let sig = signature(SPAM).unwrap();
+
+        for expected in 0..3 {
+            let similar = recent
+                .count_similar_and_record("tenant_a", sig, window, THRESHOLD)
+                .await;
+            assert_eq!(similar, expected);
+        }
+        assert_eq!(
+            recent
+                .count_similar_and_record("tenant_b", sig, window, THRESHOLD)
+                .await,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn only_counts_the_window() {
+        let recent = RecentComments::new();
+        let sig = signature(SPAM).unwrap();
+        let window = Duration::from_millis(20);
+
+        recent
+            .count_similar_and_record("scope", sig, window, THRESHOLD)
+            .await;
+        std::thread::sleep(Duration::from_millis(40));
+
+        assert_eq!(
+            recent
+                .count_similar_and_record("scope", sig, window, THRESHOLD)
+                .await,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_a_bounded_history_per_scope() {
+        let recent = RecentComments::new();
+        let window = Duration::from_secs(60);
+
+        for _ in 0..MAX_RECENT_PER_SCOPE + 10 {
+            recent
+                .count_similar_and_record("scope", 1, window, 100)
+                .await;
+        }
+
+        assert_eq!(
+            recent
+                .count_similar_and_record("scope", 1, window, 100)
+                .await,
+            MAX_RECENT_PER_SCOPE
+        );
+    }
+}
@@ -20,6 +20,8 @@ pub enum Error {
     Internal,
     #[error("unauthorized")]
     Unauthorized,
+    #[error("version conflict")]
+    Conflict,
 }
 
 #[derive(Serialize)]
@@ -32,11 +34,20 @@ impl IntoResponse for Error {
     fn into_response(self) -> Response {
         let (status, msg) = match &self {
             Error::Validation(m) => (StatusCode::BAD_REQUEST, m.to_string()),
+            Error::Db(sqlx::Error::Database(db))
+                if db.is_check_violation() || db.is_foreign_key_violation() =>
+            {
+                (StatusCode::BAD_REQUEST, db.message().to_string())
+            }
             Error::Db(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
             Error::Regex(m) => (StatusCode::BAD_REQUEST, m.to_string()),
             Error::NotFound => (StatusCode::NOT_FOUND, "not found".to_string()),
             Error::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "internal".to_string()),
             Error::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized".to_string()),
+            Error::Conflict => (
+                StatusCode::PRECONDITION_FAILED,
+                "resource was modified, reload and try again".to_string(),
+            ),
         };
         (
             status,
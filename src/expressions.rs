@@ -0,0 +1,397 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use std::collections::HashMap;
+
+use crate::{
+    errors::Error,
+    models::{CommentRequest, RuleKind},
+};
+
+/// Deeper or bigger expressions are rejected on insert
+const MAX_DEPTH: usize = 8;
+const MAX_NODES: usize = 64;
+
+/// Boolean combination of rule hits and comment context, stored as JSON, e.g.
+/// `{"and": [{"regex": {"id": 3}}, {"not": {"bad_word": {"id": 7, "min_hits": 2}}}]}` or
+/// `{"and": [{"domain_rule": {"id": 2}}, {"account_age_below": 86400}]}` or
+/// `{"and": [{"ip_hash": "9f86d081884c7d65"}, {"not": {"author": "u42"}}]}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum RuleExpr {
+    /// True when the bad word hit at least `min_hits` times
+    BadWord {
+        id: i32,
+        #[serde(default = "one")]
+        min_hits: u32,
+    },
+    /// True when the regex rule matched at least `min_hits` times
+    Regex {
+        id: i32,
+        #[serde(default = "one")]
+        min_hits: u32,
+    },
+    /// True when links denied by the domain rule were found at least `min_hits` times
+    DomainRule {
+        id: i32,
+        #[serde(default = "one")]
+        min_hits: u32,
+    },
+    /// True when the author's account is younger than this many seconds, false when unknown
+    AccountAgeBelow(i64),
+    /// True when the comment carries the tag
+    Tag(String),
+    /// True when the comment was posted to the thread
+    Thread(String),
+    /// True when the comment was posted by the author
+    Author(String),
+    /// True when the comment came from the hashed IP, compared case-insensitively
+    IpHash(String),
+    And(Vec<RuleExpr>),
+    Or(Vec<RuleExpr>),
+    Not(Box<RuleExpr>),
+}
+
+fn one() -> u32 {
+    1
+}
+
+/// Enforced hits per rule of the comment being moderated
+pub type RuleHits = HashMap<(RuleKind, i32), u32>;
+
+impl RuleExpr {
+    pub fn evaluate(&self, hits: &RuleHits, req: &CommentRequest) -> bool {
+        let count = |kind, id| hits.get(&(kind, id)).copied().unwrap_or_default();
+
+        match self {
+            RuleExpr::BadWord { id, min_hits } => count(RuleKind::BadWord, *id) >= *min_hits,
+            RuleExpr::Regex { id, min_hits } => count(RuleKind::Regex, *id) >= *min_hits,
+            RuleExpr::DomainRule { id, min_hits } => count(RuleKind::DomainRule, *id) >= *min_hits,
+            RuleExpr::AccountAgeBelow(secs) => req.account_age_secs.is_some_and(|age| age < *secs),
+            RuleExpr::Tag(tag) => req.tags.contains(tag),
+            RuleExpr::Thread(thread) => req.thread_id.as_ref() == Some(thread),
+            RuleExpr::Author(author) => req.author_id.as_ref() == Some(author),
+            RuleExpr::IpHash(hash) => req
+                .ip_hash
+                .as_ref()
+                .is_some_and(|ip_hash| ip_hash.eq_ignore_ascii_case(hash)),
+            RuleExpr::And(items) => items.iter().all(|e| e.evaluate(hits, req)),
+            RuleExpr::Or(items) => items.iter().any(|e| e.evaluate(hits, req)),
+            RuleExpr::Not(inner) => !inner.evaluate(hits, req),
+        }
+    }
+
+    /// Checks the shape of the expression and that every referenced rule exists
+    pub async fn validate(&self, conn: &mut PgConnection) -> Result<(), Error> {
+        self.check_shape()?;
+
+        let mut refs = Vec::new();
+        self.references(&mut refs);
+        if let Some((table, id)) = missing_reference(conn, &refs).await? {
+            return Err(Error::Validation(format!(
+                "expression references unknown {table} rule {id}"
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn check_shape(&self) -> Result<(), Error> {
+        let mut nodes = 0;
+        self.check(1, &mut nodes)?;
+
+        // e.g. a bare NOT, it would match every clean comment
+        if self.evaluate(&RuleHits::new(), &CommentRequest::default()) {
+            return Err(Error::Validation(
+                "expression must not match a comment without any rule hits or context".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Rules the expression looks at
+    fn references(&self, refs: &mut Vec<(RuleKind, i32)>) {
+        match self {
+            RuleExpr::BadWord { id, .. } => refs.push((RuleKind::BadWord, *id)),
+            RuleExpr::Regex { id, .. } => refs.push((RuleKind::Regex, *id)),
+            RuleExpr::DomainRule { id, .. } => refs.push((RuleKind::DomainRule, *id)),
+            RuleExpr::AccountAgeBelow(_)
+            | RuleExpr::Tag(_)
+            | RuleExpr::Thread(_)
+            | RuleExpr::Author(_)
+            | RuleExpr::IpHash(_) => {}
+            RuleExpr::And(items) | RuleExpr::Or(items) => {
+                items.iter().for_each(|e| e.references(refs))
+            }
+            RuleExpr::Not(inner) => inner.references(refs),
+        }
+    }
+
+    fn check(&self, depth: usize, nodes: &mut usize) -> Result<(), Error> {
+        *nodes += 1;
+        if depth > MAX_DEPTH || *nodes > MAX_NODES {
+            return Err(Error::Validation(format!(
+                "expression is limited to a depth of {MAX_DEPTH} and {MAX_NODES} terms"
+            )));
+        }
+
+        match self {
+            RuleExpr::BadWord { min_hits, .. }
+            | RuleExpr::Regex { min_hits, .. }
+            | RuleExpr::DomainRule { min_hits, .. }
+                if *min_hits == 0 =>
+            {
+                Err(Error::Validation("min_hits must be at least 1".into()))
+            }
+            RuleExpr::BadWord { .. } | RuleExpr::Regex { .. } | RuleExpr::DomainRule { .. } => {
+                Ok(())
+            }
+            RuleExpr::AccountAgeBelow(secs) if *secs <= 0 => Err(Error::Validation(
+                "account_age_below must be a positive number of seconds".into(),
+            )),
+            RuleExpr::Tag(value) | RuleExpr::Thread(value) | RuleExpr::Author(value)
+                if value.is_empty() || value.len() > 128 =>
+            {
+                Err(Error::Validation(
+                    "tag, thread and author must be 1 to 128 characters".into(),
+                ))
+            }
+            // Same shape as the `ip_hash` of a comment
+            RuleExpr::IpHash(hash)
+                if !(16..=128).contains(&hash.len())
+                    || !hash.chars().all(|c| c.is_ascii_hexdigit()) =>
+            {
+                Err(Error::Validation(
+                    "ip_hash must be 16 to 128 hex digits".into(),
+                ))
+            }
+            RuleExpr::AccountAgeBelow(_)
+            | RuleExpr::Tag(_)
+            | RuleExpr::Thread(_)
+            | RuleExpr::Author(_)
+            | RuleExpr::IpHash(_) => Ok(()),
+            RuleExpr::And(items) | RuleExpr::Or(items) => {
+                if items.is_empty() {
+                    return Err(Error::Validation("and/or need at least one term".into()));
+                }
+                items.iter().try_for_each(|e| e.check(depth + 1, nodes))
+            }
+            RuleExpr::Not(inner) => inner.check(depth + 1, nodes),
+        }
+    }
+}
+
+/// First of `refs` whose rule doesn't exist, as its table and id
+async fn missing_reference(
+    conn: &mut PgConnection,
+    refs: &[(RuleKind, i32)],
+) -> Result<Option<(&'static str, i32)>, Error> {
+    for &(kind, id) in refs {
+        let table = match kind {
+            RuleKind::BadWord => "bad_words",
+            RuleKind::DomainRule => "domain_rules",
+            _ => "regex_rules",
+        };
+        let exists: bool = sqlx::query_scalar(&format!(
+            "SELECT EXISTS (SELECT 1 FROM {table} WHERE id = $1)"
+        ))
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+        if !exists {
+            return Ok(Some((table, id)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Fails when a stored expression references a rule that's gone, run in the transaction that
+/// deleted rules so it rolls back. A dangling reference never hits, a `not` over it would
+/// match every comment.
+pub async fn check_references(conn: &mut PgConnection) -> Result<(), Error> {
+    let exprs: Vec<(i32, serde_json::Value)> =
+        sqlx::query_as("SELECT id, expression FROM rule_expressions ORDER BY id")
+            .fetch_all(&mut *conn)
+            .await?;
+
+    for (expr_id, expr) in exprs {
+        let expr: RuleExpr = serde_json::from_value(expr)
+            .map_err(|e| Error::Validation(format!("rule expression {expr_id}: {e}")))?;
+        let mut refs = Vec::new();
+        expr.references(&mut refs);
+        if let Some((table, id)) = missing_reference(conn, &refs).await? {
+            return Err(Error::Validation(format!(
+                "{table} rule {id} is referenced by rule expression {expr_id}, change or delete the expression first"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expr(json: &str) -> RuleExpr {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn hits(entries: &[(RuleKind, i32, u32)]) -> RuleHits {
+        entries
+            .iter()
+            .map(|&(kind, id, count)| ((kind, id), count))
+            .collect()
+    }
+
+    #[test]
+    fn min_hits_defaults_to_one() {
+        let word = expr(r#"{"bad_word": {"id": 7}}"#);
+        let twice = expr(r#"{"regex": {"id": 3, "min_hits": 2}}"#);
+        let req = CommentRequest::default();
+
+        assert!(!word.evaluate(&hits(&[]), &req));
+        assert!(word.evaluate(&hits(&[(RuleKind::BadWord, 7, 1)]), &req));
+        // Hits of another kind with the same id don't count
+        assert!(!word.evaluate(&hits(&[(RuleKind::Regex, 7, 1)]), &req));
+
+        assert!(!twice.evaluate(&hits(&[(RuleKind::Regex, 3, 1)]), &req));
+        assert!(twice.evaluate(&hits(&[(RuleKind::Regex, 3, 2)]), &req));
+    }
+
+    #[test]
+    fn combinators() {
+        let e = expr(
+            r#"{"and": [
+                {"or": [{"bad_word": {"id": 1}}, {"domain_rule": {"id": 2}}]},
+                {"not": {"regex": {"id": 3}}}
+            ]}"#,
+        );
+        let req = CommentRequest::default();
+
+        assert!(e.evaluate(&hits(&[(RuleKind::BadWord, 1, 1)]), &req));
+        assert!(e.evaluate(&hits(&[(RuleKind::DomainRule, 2, 1)]), &req));
+        assert!(!e.evaluate(&hits(&[]), &req));
+        assert!(!e.evaluate(
+            &hits(&[(RuleKind::BadWord, 1, 1), (RuleKind::Regex, 3, 1)]),
+            &req
+        ));
+    }
+
+    #[test]
+    fn comment_context() {
+        let e = expr(
+            r#"{"and": [
+                {"account_age_below": 86400},
+                {"tag": "news"},
+                {"thread": "t1"},
+                {"author": "u1"},
+                {"ip_hash": "9F86D081884C7D65"}
+            ]}"#,
+        );
+        let mut req = CommentRequest {
+            account_age_secs: Some(3600),
+            tags: vec!["news".into()],
+            thread_id: Some("t1".into()),
+            author_id: Some("u1".into()),
+            ip_hash: Some("9f86d081884c7d65".into()),
+            ..CommentRequest::default()
+        };
+        assert!(e.evaluate(&hits(&[]), &req));
+
+        req.account_age_secs = Some(86400);
+        assert!(!e.evaluate(&hits(&[]), &req));
+        // An unknown account age is never below the limit
+        req.account_age_secs = None;
+        assert!(!e.evaluate(&hits(&[]), &req));
+    }
+
+    #[test]
+    fn rejects_expressions_matching_an_empty_comment() {
+        for json in [
+            r#"{"not": {"bad_word": {"id": 1}}}"#,
+            r#"{"or": [{"tag": "x"}, {"not": {"thread": "t"}}]}"#,
+            r#"{"not": {"account_age_below": 60}}"#,
+        ] {
+            assert!(
+                matches!(expr(json).check_shape(), Err(Error::Validation(_))),
+                "{json}"
+            );
+        }
+
+        assert!(
+            expr(r#"{"and": [{"tag": "x"}, {"not": {"bad_word": {"id": 1}}}]}"#)
+                .check_shape()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_terms() {
+        for json in [
+            r#"{"bad_word": {"id": 1, "min_hits": 0}}"#,
+            r#"{"account_age_below": 0}"#,
+            r#"{"tag": ""}"#,
+            r#"{"and": []}"#,
+            r#"{"ip_hash": "not-hex-at-all-xyz"}"#,
+            r#"{"ip_hash": "abc"}"#,
+        ] {
+            assert!(
+                matches!(expr(json).check_shape(), Err(Error::Validation(_))),
+                "{json}"
+            );
+        }
+    }
+
+    #[test]
+    fn depth_limit() {
+        let nest = |depth: usize| {
+            let mut e = RuleExpr::Tag("x".into());
+            for _ in 1..depth {
+                e = RuleExpr::And(vec![e]);
+            }
+            e
+        };
+
+        assert!(nest(MAX_DEPTH).check_shape().is_ok());
+        assert!(matches!(
+            nest(MAX_DEPTH + 1).check_shape(),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn node_limit() {
+        let terms = |count: usize| {
+            RuleExpr::Or(
+                (0..count as i32)
+                    .map(|id| RuleExpr::BadWord { id, min_hits: 1 })
+                    .collect(),
+            )
+        };
+
+        // The `or` itself is a node too
+        assert!(terms(MAX_NODES - 1).check_shape().is_ok());
+        assert!(matches!(
+            terms(MAX_NODES).check_shape(),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn collects_references() {
+        let mut refs = Vec::new();
+        expr(r#"{"and": [{"bad_word": {"id": 1}}, {"not": {"or": [{"regex": {"id": 2}}, {"domain_rule": {"id": 3}}]}}]}"#)
+            .references(&mut refs);
+
+        assert_eq!(
+            refs,
+            [
+                (RuleKind::BadWord, 1),
+                (RuleKind::Regex, 2),
+                (RuleKind::DomainRule, 3)
+            ]
+        );
+    }
+}
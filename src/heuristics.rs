@@ -0,0 +1,213 @@
+use serde::Serialize;
+
+/// Caps ratio is meaningless for a handful of letters, e.g. "OK" or "TBH"
+const MIN_LETTERS_FOR_CAPS: u32 = 10;
+
+/// Content signals that don't depend on any rule. Each one is enabled through the
+/// `heuristic_<key>_threshold` setting, `heuristic_<key>_action` picks what a hit does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Signal {
+    /// Longest run of the same character, e.g. "cooool" is 4
+    RepeatedChars,
+    /// Longest run of the same word in a row
+    RepeatedWords,
+    /// Percentage of uppercase letters among all letters
+    CapsRatio,
+    /// Percentage of emoji and other symbols among the non-whitespace characters
+    SymbolDensity,
+    /// Length in characters, hits when shorter than the threshold
+    ShortContent,
+    /// Length in characters
+    LongContent,
+    /// Percentage of non-letters among the non-whitespace characters
+    NonLetterRatio,
+}
+
+impl Signal {
+    /// Used in the setting keys
+    pub fn key(self) -> &'static str {
+        match self {
+            Signal::RepeatedChars => "repeated_chars",
+            Signal::RepeatedWords => "repeated_words",
+            Signal::CapsRatio => "caps_ratio",
+            Signal::SymbolDensity => "symbol_density",
+            Signal::ShortContent => "short_content",
+            Signal::LongContent => "long_content",
+            Signal::NonLetterRatio => "non_letter_ratio",
+        }
+    }
+
+    /// Reason code reported for a hit of the signal
+    pub fn reason_code(self) -> &'static str {
+        match self {
+            Signal::RepeatedChars => "REPEATED_CHARS",
+            Signal::RepeatedWords => "REPEATED_WORDS",
+            Signal::CapsRatio => "EXCESSIVE_CAPS",
+            Signal::SymbolDensity => "SYMBOL_FLOOD",
+            Signal::ShortContent => "TOO_SHORT",
+            Signal::LongContent => "TOO_LONG",
+            Signal::NonLetterRatio => "NON_LETTER_FLOOD",
+        }
+    }
+
+    /// Every signal but the short content one hits at or above its threshold
+    pub fn hits(self, value: u32, threshold: u32) -> bool {
+        match self {
+            Signal::ShortContent => value < threshold,
+            _ => value >= threshold,
+        }
+    }
+}
+
+/// A signal that passed its threshold, reported in the moderation response
+#[derive(Debug, Clone, Serialize)]
+pub struct SignalMatch {
+    pub signal: Signal,
+    pub value: u32,
+    pub threshold: u32,
+}
+
+/// Value of every signal for the original, not lowercased, content
+pub fn measure(content: &str) -> Vec<(Signal, u32)> {
+    let mut longest_char_run = 0;
+    let mut char_run = 0;
+    let mut previous = None;
+    let (mut chars, mut visible, mut letters, mut upper, mut symbols) = (0, 0, 0, 0, 0);
+
+    for c in content.chars() {
+        chars += 1;
+
+        char_run = if previous == Some(c) { char_run + 1 } else { 1 };
+        previous = Some(c);
+        if !c.is_whitespace() {
+            longest_char_run = longest_char_run.max(char_run);
+            visible += 1;
+        }
+
+        if c.is_alphabetic() {
+            letters += 1;
+            if c.is_uppercase() {
+                upper += 1;
+            }
+        } else if !c.is_whitespace() && !c.is_numeric() && !c.is_ascii_punctuation() {
+            symbols += 1;
+        }
+    }
+
+    let mut longest_word_run = 0;
+    let mut word_run = 0;
+    let mut previous_word = String::new();
+    for word in content
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        let word = word.to_lowercase();
+        word_run = if word == previous_word {
+            word_run + 1
+        } else {
+            1
+        };
+        longest_word_run = longest_word_run.max(word_run);
+        previous_word = word;
+    }
+
+    let percent = |part: u32, whole: u32| (part * 100).checked_div(whole).unwrap_or_default();
+    let caps = if letters >= MIN_LETTERS_FOR_CAPS {
+        percent(upper, letters)
+    } else {
+        0
+    };
+
+    vec![
+        (Signal::RepeatedChars, longest_char_run),
+        (Signal::RepeatedWords, longest_word_run),
+        (Signal::CapsRatio, caps),
+        (Signal::SymbolDensity, percent(symbols, visible)),
+        (Signal::ShortContent, chars),
+        (Signal::LongContent, chars),
+        (Signal::NonLetterRatio, percent(visible - letters, visible)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(content: &str, signal: Signal) -> u32 {
+        measure(content)
+            .into_iter()
+            .find(|&(s, _)| s == signal)
+            .map(|(_, value)| value)
+            .unwrap()
+    }
+
+    #[test]
+    fn repeated_chars() {
+        assert_eq!(value("cooool", Signal::RepeatedChars), 4);
+        assert_eq!(value("!!!!!!", Signal::RepeatedChars), 6);
+        // Whitespace runs don't count
+        assert_eq!(value("a      b", Signal::RepeatedChars), 1);
+        assert_eq!(value("🔥🔥🔥", Signal::RepeatedChars), 3);
+    }
+
+    #[test]
+    fn repeated_words() {
+        assert_eq!(value("buy buy BUY now", Signal::RepeatedWords), 3);
+        assert_eq!(value("buy, buy! buy", Signal::RepeatedWords), 3);
+        assert_eq!(value("a b a b", Signal::RepeatedWords), 1);
+        assert_eq!(value("çok çok güzel", Signal::RepeatedWords), 2);
+    }
+
+    #[test]
+    fn caps_ratio() {
+        assert_eq!(value("HELLO WORLD", Signal::CapsRatio), 100);
+        assert_eq!(value("Hello World", Signal::CapsRatio), 20);
+        assert_eq!(value("İSTANBUL ÇOK GÜZEL", Signal::CapsRatio), 100);
+        assert_eq!(value("ΑΒΓΔΕΖΗΘΙΚ", Signal::CapsRatio), 100);
+        // Too few letters to judge
+        assert_eq!(value("OK TBH", Signal::CapsRatio), 0);
+        // Scripts without case are never shouting
+        assert_eq!(value("你好你好你好你好你好", Signal::CapsRatio), 0);
+    }
+
+    #[test]
+    fn symbol_density() {
+        assert_eq!(value("🔥🔥🔥", Signal::SymbolDensity), 100);
+        assert_eq!(value("hi 🔥", Signal::SymbolDensity), 33);
+        // ASCII punctuation and digits aren't symbols
+        assert_eq!(value("ok!? 42", Signal::SymbolDensity), 0);
+    }
+
+    #[test]
+    fn content_length() {
+        assert_eq!(value("héllo", Signal::ShortContent), 5);
+        assert_eq!(value("héllo wörld", Signal::LongContent), 11);
+        assert_eq!(value("🔥🔥", Signal::LongContent), 2);
+    }
+
+    #[test]
+    fn non_letter_ratio() {
+        assert_eq!(value("abc 123 !!", Signal::NonLetterRatio), 62);
+        assert_eq!(value("🔥🔥🔥", Signal::NonLetterRatio), 100);
+        assert_eq!(value("merhaba", Signal::NonLetterRatio), 0);
+    }
+
+    #[test]
+    fn empty_content() {
+        assert!(measure("").iter().all(|&(_, value)| value == 0));
+        assert!(measure("   ").iter().all(|&(signal, value)| match signal {
+            Signal::ShortContent | Signal::LongContent => value == 3,
+            _ => value == 0,
+        }));
+    }
+
+    #[test]
+    fn thresholds() {
+        assert!(Signal::ShortContent.hits(0, 5));
+        assert!(Signal::ShortContent.hits(4, 5));
+        assert!(!Signal::ShortContent.hits(5, 5));
+        assert!(Signal::CapsRatio.hits(70, 70));
+        assert!(!Signal::CapsRatio.hits(69, 70));
+    }
+}
@@ -0,0 +1,196 @@
+use serde_json::Value;
+use sqlx::PgConnection;
+
+use crate::{
+    errors::Error,
+    models::{ApiKeyId, RuleKind, RuleOperation},
+};
+
+impl RuleKind {
+    fn table(self) -> &'static str {
+        match self {
+            RuleKind::BadWord => "bad_words",
+            RuleKind::Regex => "regex_rules",
+            RuleKind::Setting => "settings",
+            RuleKind::AllowWord => "allow_words",
+            RuleKind::ReasonTemplate => "reason_templates",
+            RuleKind::Category => "categories",
+            RuleKind::CategoryThreshold => "category_thresholds",
+            RuleKind::Expression => "rule_expressions",
+            RuleKind::PiiDetector => "pii_detectors",
+            RuleKind::DomainRule => "domain_rules",
+            RuleKind::ReputationRule => "reputation_rules",
+        }
+    }
+
+    fn key_column(self) -> &'static str {
+        match self {
+            RuleKind::BadWord
+            | RuleKind::Regex
+            | RuleKind::AllowWord
+            | RuleKind::ReasonTemplate
+            | RuleKind::CategoryThreshold
+            | RuleKind::Expression
+            | RuleKind::PiiDetector
+            | RuleKind::DomainRule
+            | RuleKind::ReputationRule => "id",
+            RuleKind::Setting => "key",
+            RuleKind::Category => "name",
+        }
+    }
+
+    fn is_versioned(self) -> bool {
+        !matches!(self, RuleKind::Setting | RuleKind::Category)
+    }
+}
+
+/// Current row as Postgres sees it, snapshots taken this way can be fed back to [`restore`]
+pub async fn snapshot(
+    conn: &mut PgConnection,
+    kind: RuleKind,
+    key: &str,
+) -> Result<Option<Value>, Error> {
+    let query = format!(
+        "SELECT to_jsonb(t) FROM {} t WHERE t.{}::text = $1",
+        kind.table(),
+        kind.key_column()
+    );
+
+    Ok(sqlx::query_scalar(&query)
+        .bind(key)
+        .fetch_optional(conn)
+        .await?)
+}
+
+pub async fn record(
+    conn: &mut PgConnection,
+    actor: &ApiKeyId,
+    kind: RuleKind,
+    key: &str,
+    operation: RuleOperation,
+    before: Option<Value>,
+    after: Option<Value>,
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT INTO rule_history (rule_kind, rule_key, operation, actor, before, after)
+         VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(kind)
+    .bind(key)
+    .bind(operation)
+    .bind(&actor.0)
+    .bind(before)
+    .bind(after)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Records a mutation that already ran on `conn`, reading the `after` side from the table
+pub async fn record_change(
+    conn: &mut PgConnection,
+    actor: &ApiKeyId,
+    kind: RuleKind,
+    key: &str,
+    operation: RuleOperation,
+    before: Option<Value>,
+) -> Result<(), Error> {
+    let after = snapshot(&mut *conn, kind, key).await?;
+    record(conn, actor, kind, key, operation, before, after).await
+}
+
+/// Columns the table has now, snapshots taken before a migration may lack some of them
+async fn columns(conn: &mut PgConnection, kind: RuleKind) -> Result<Vec<String>, Error> {
+    Ok(sqlx::query_scalar(
+        "SELECT column_name::text FROM information_schema.columns
+         WHERE table_schema = current_schema() AND table_name = $1
+         ORDER BY ordinal_position",
+    )
+    .bind(kind.table())
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Puts the row back to `target`, deleting it when `target` is `None`. An existing row is
+/// updated in place so rows referencing it, e.g. the thresholds of a category, stay put.
+/// Columns missing from `target` keep their current value, or take their default when the
+/// row is gone. Versioned rules get a version above both the current and the restored one so
+/// clients holding an old ETag can't overwrite the rollback.
+pub async fn restore(
+    conn: &mut PgConnection,
+    kind: RuleKind,
+    key: &str,
+    target: Option<&Value>,
+) -> Result<(), Error> {
+    let current = snapshot(&mut *conn, kind, key).await?;
+
+    let Some(target) = target else {
+        let delete = format!(
+            "DELETE FROM {} WHERE {}::text = $1",
+            kind.table(),
+            kind.key_column()
+        );
+        sqlx::query(&delete).bind(key).execute(&mut *conn).await?;
+        return Ok(());
+    };
+
+    let mut merged = match &current {
+        Some(Value::Object(row)) => row.clone(),
+        _ => serde_json::Map::new(),
+    };
+    if let Value::Object(snapshot) = target {
+        merged.extend(snapshot.clone());
+    }
+
+    let columns = columns(&mut *conn, kind)
+        .await?
+        .into_iter()
+        .filter(|c| merged.contains_key(c))
+        .map(|c| format!("\"{c}\""))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let query = match &current {
+        Some(_) => format!(
+            "UPDATE {table} SET ({columns}) = (SELECT {columns} FROM jsonb_populate_record(NULL::{table}, $1)) WHERE {key}::text = $2",
+            table = kind.table(),
+            key = kind.key_column()
+        ),
+        None => format!(
+            "INSERT INTO {table} ({columns}) SELECT {columns} FROM jsonb_populate_record(NULL::{table}, $1)",
+            table = kind.table()
+        ),
+    };
+    let mut query = sqlx::query(&query).bind(Value::Object(merged));
+    if current.is_some() {
+        query = query.bind(key);
+    }
+    query.execute(&mut *conn).await.map_err(|e| match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            Error::Validation("another rule conflicts with the restored version".to_string())
+        }
+        _ => Error::Db(e),
+    })?;
+
+    if kind.is_versioned() {
+        let version_of = |v: Option<&Value>| {
+            v.and_then(|v| v.get("version"))
+                .and_then(Value::as_i64)
+                .unwrap_or(0)
+        };
+        let next = version_of(current.as_ref()).max(version_of(Some(target))) + 1;
+
+        let bump = format!(
+            "UPDATE {} SET version = $2 WHERE {}::text = $1",
+            kind.table(),
+            kind.key_column()
+        );
+        sqlx::query(&bump)
+            .bind(key)
+            .bind(next as i32)
+            .execute(conn)
+            .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,11 @@
+/// ISO 639-3 code of the text's language, `None` when it can't be told reliably, e.g. for
+/// very short comments
+pub fn detect(text: &str) -> Option<&'static str> {
+    let info = whatlang::detect(text)?;
+    info.is_reliable().then(|| info.lang().code())
+}
+
+/// Whether `code` is an ISO 639-3 code the detector can return
+pub fn is_known(code: &str) -> bool {
+    whatlang::Lang::from_code(code).is_some()
+}
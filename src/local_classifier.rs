@@ -0,0 +1,331 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use crate::{
+    cache::RuleCode,
+    detectors::{spanless_action, DetectionContext, Detector, DetectorFuture, Match},
+    models::RuleMode,
+    normalize::NormalizedText,
+    reasons::Reason,
+};
+
+const LOCAL_CLASSIFIER_CODE: &str = "LOCAL_CLASSIFIER";
+/// Longer n-grams barely generalize and blow up the lookups per comment
+const MAX_NGRAM: usize = 5;
+/// How often the model file is checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ModelError {
+    #[error("read failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("parse failed: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("invalid model: {0}")]
+    Invalid(String),
+}
+
+/// Logistic regression over word n-grams, one set of weights per category, e.g.
+/// `{"min_n": 1, "max_n": 2, "categories": {"toxicity": {"bias": -3.0, "weights": {"idiot": 4.2, "shut up": 2.5}}}}`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Model {
+    #[serde(default = "one")]
+    min_n: usize,
+    #[serde(default = "one")]
+    max_n: usize,
+    categories: BTreeMap<String, CategoryWeights>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CategoryWeights {
+    #[serde(default)]
+    bias: f64,
+    /// Keyed by lowercase n-grams, words joined by a single space
+    weights: HashMap<String, f64>,
+}
+
+fn one() -> usize {
+    1
+}
+
+impl Model {
+    fn load(path: &Path) -> Result<Self, ModelError> {
+        let model: Model = serde_json::from_slice(&std::fs::read(path)?)?;
+
+        if model.min_n == 0 || model.min_n > model.max_n || model.max_n > MAX_NGRAM {
+            return Err(ModelError::Invalid(format!(
+                "n-gram sizes must satisfy 1 <= min_n <= max_n <= {MAX_NGRAM}"
+            )));
+        }
+        // Category names end up in setting keys
+        if let Some(category) = model.categories.keys().find(|c| {
+            c.is_empty()
+                || !c
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        }) {
+            return Err(ModelError::Invalid(format!(
+                "category {category:?} must be lowercase letters, digits and underscores"
+            )));
+        }
+
+        Ok(model)
+    }
+
+    /// Distinct word n-grams of the normalized text
+    fn ngrams(&self, text: &str) -> HashSet<String> {
+        let words: Vec<&str> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .collect();
+
+        (self.min_n..=self.max_n)
+            .flat_map(|n| words.windows(n).map(|gram| gram.join(" ")))
+            .collect()
+    }
+
+    /// Probability between 0 and 1 per category
+    fn score(&self, text: &str) -> Vec<(&str, f64)> {
+        let ngrams = self.ngrams(text);
+
+        self.categories
+            .iter()
+            .map(|(category, weights)| {
+                let logit = weights.bias
+                    + ngrams
+                        .iter()
+                        .filter_map(|gram| weights.weights.get(gram))
+                        .sum::<f64>();
+                (category.as_str(), 1.0 / (1.0 + (-logit).exp()))
+            })
+            .collect()
+    }
+}
+
+/// In-process stage scoring comments with the model at `LOCAL_CLASSIFIER_MODEL`. Scores map to
+/// actions through `local_classifier_<category>_threshold` and `local_classifier_<category>_action`,
+/// categories without a threshold are only scored.
+pub struct LocalClassifier {
+    path: PathBuf,
+    model: RwLock<Arc<Model>>,
+    /// Modification time of the loaded file
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl LocalClassifier {
+    /// Called once on startup, the service doesn't start with a broken model
+    pub fn load(path: PathBuf) -> Result<Self, ModelError> {
+        let modified = std::fs::metadata(&path)?.modified().ok();
+        let model = Model::load(&path)?;
+
+        info!(
+            "Loaded local classifier model | Path: {} | Categories: {}",
+            path.display(),
+            model.categories.len()
+        );
+
+        Ok(Self {
+            path,
+            model: RwLock::new(Arc::new(model)),
+            modified: Mutex::new(modified),
+        })
+    }
+
+    /// Swaps in the model file if it changed since the last load, a broken file keeps the
+    /// current model
+    fn reload_if_changed(&self) -> Result<bool, ModelError> {
+        let modified = std::fs::metadata(&self.path)?.modified().ok();
+        if modified == *self.modified.lock().unwrap() {
+            return Ok(false);
+        }
+
+        let model = Model::load(&self.path)?;
+        *self.model.write().unwrap() = Arc::new(model);
+        *self.modified.lock().unwrap() = modified;
+
+        Ok(true)
+    }
+}
+
+impl Detector for LocalClassifier {
+    fn name(&self) -> &'static str {
+        "local_classifier"
+    }
+
+    fn evaluate<'a>(
+        &'a self,
+        text: &'a NormalizedText,
+        ctx: &'a DetectionContext<'a>,
+    ) -> DetectorFuture<'a> {
+        let model = self.model.read().unwrap().clone();
+
+        Box::pin(async move {
+            let cache = ctx.cache;
+
+            let mut matches = Vec::new();
+            for (category, score) in model.score(&text.text) {
+                let Some(threshold): Option<f64> = cache
+                    .parsed_setting(&format!("local_classifier_{category}_threshold"))
+                    .await
+                else {
+                    continue;
+                };
+                if score < threshold {
+                    continue;
+                }
+
+                let action =
+                    spanless_action(cache, &format!("local_classifier_{category}_action")).await;
+                matches.push(Match {
+                    rule: None,
+                    action,
+                    mode: RuleMode::Enforce,
+                    code: RuleCode {
+                        reason_code: LOCAL_CLASSIFIER_CODE.to_string(),
+                        category: category.to_string(),
+                    },
+                    reason: Reason::Classifier {
+                        category: category.to_string(),
+                        score,
+                    },
+                    hits: 1,
+                    weight: 0,
+                    spans: Vec::new(),
+                });
+            }
+
+            matches
+        })
+    }
+}
+
+/// Periodically picks up a replaced model file
+pub async fn watch(classifier: Arc<LocalClassifier>) {
+    loop {
+        tokio::time::sleep(RELOAD_INTERVAL).await;
+
+        let reloaded = {
+            let classifier = classifier.clone();
+            tokio::task::spawn_blocking(move || classifier.reload_if_changed()).await
+        };
+        match reloaded {
+            Ok(Ok(true)) => info!(
+                "Reloaded local classifier model | Path: {}",
+                classifier.path.display()
+            ),
+            Ok(Ok(false)) => {}
+            Ok(Err(e)) => error!("Local classifier model reload failed: {}", e),
+            Err(e) => error!("Local classifier model reload panicked: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `json` to a file of its own under the temp dir and loads it
+    fn load(name: &str, json: &str) -> Result<Model, ModelError> {
+        let path = std::env::temp_dir().join(format!(
+            "local_classifier_{}_{name}.json",
+            std::process::id()
+        ));
+        std::fs::write(&path, json).unwrap();
+        let model = Model::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        model
+    }
+
+    fn model() -> Model {
+        load(
+            "model",
+            r#"{"min_n": 1, "max_n": 2, "categories": {
+                "toxicity": {"bias": -3.0, "weights": {"idiot": 4.2, "shut up": 2.5}},
+                "spam": {"weights": {"buy now": 1.0}}
+            }}"#,
+        )
+        .unwrap()
+    }
+
+    fn sigmoid(logit: f64) -> f64 {
+        1.0 / (1.0 + (-logit).exp())
+    }
+
+    #[test]
+    fn load_defaults_to_unigrams() {
+        let model = load("defaults", r#"{"categories": {}}"#).unwrap();
+        assert_eq!((model.min_n, model.max_n), (1, 1));
+    }
+
+    #[test]
+    fn load_rejects_invalid_models() {
+        for (name, json) in [
+            ("zero", r#"{"min_n": 0, "categories": {}}"#),
+            ("reversed", r#"{"min_n": 3, "max_n": 2, "categories": {}}"#),
+            ("too_long", r#"{"max_n": 6, "categories": {}}"#),
+            ("uppercase", r#"{"categories": {"Toxic": {"weights": {}}}}"#),
+            ("empty_name", r#"{"categories": {"": {"weights": {}}}}"#),
+        ] {
+            assert!(
+                matches!(load(name, json), Err(ModelError::Invalid(_))),
+                "{name}"
+            );
+        }
+
+        for (name, json) in [
+            ("unknown_field", r#"{"categories": {}, "threshold": 0.5}"#),
+            (
+                "missing_weights",
+                r#"{"categories": {"toxicity": {"bias": 1.0}}}"#,
+            ),
+            ("broken", r#"{"categories": "#),
+        ] {
+            assert!(
+                matches!(load(name, json), Err(ModelError::Parse(_))),
+                "{name}"
+            );
+        }
+
+        assert!(matches!(
+            Model::load(Path::new("/nonexistent/model.json")),
+            Err(ModelError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn ngrams_span_punctuation() {
+        let ngrams = model().ngrams("shut up, you idiot!");
+
+        let mut expected = vec![
+            "shut",
+            "up",
+            "you",
+            "idiot",
+            "shut up",
+            "up you",
+            "you idiot",
+        ];
+        expected.sort();
+        let mut ngrams: Vec<&str> = ngrams.iter().map(String::as_str).collect();
+        ngrams.sort();
+        assert_eq!(ngrams, expected);
+    }
+
+    #[test]
+    fn score_sums_distinct_ngrams() {
+        let model = model();
+
+        let scores: BTreeMap<&str, f64> = model.score("idiot idiot, shut up").into_iter().collect();
+        // A repeated n-gram counts once
+        assert!((scores["toxicity"] - sigmoid(-3.0 + 4.2 + 2.5)).abs() < 1e-9);
+        assert!((scores["spam"] - 0.5).abs() < 1e-9);
+
+        let scores: BTreeMap<&str, f64> = model.score("hello there").into_iter().collect();
+        assert!((scores["toxicity"] - sigmoid(-3.0)).abs() < 1e-9);
+    }
+}
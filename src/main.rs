@@ -1,7 +1,24 @@
 mod cache;
+mod classifier;
+mod detectors;
+mod duplicates;
 mod errors;
+mod expressions;
+mod heuristics;
+mod history;
+mod language;
+#[cfg(feature = "local-classifier")]
+mod local_classifier;
 mod models;
+mod moderation;
+mod normalize;
+mod pii;
+mod reasons;
+mod reputation;
 mod routes;
+mod scheduler;
+mod urls;
+mod velocity;
 
 use crate::routes::{app_routes, AppContext};
 use axum::{
@@ -10,6 +27,7 @@ use axum::{
     middleware::{self, Next},
     response::{IntoResponse, Response},
 };
+use std::sync::Arc;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[macro_use(info, warn, debug, error)]
@@ -22,8 +40,32 @@ const LOG_LEVEL: &str = "info,warn,error,moderation_service=debug,axum=debug";
 #[cfg(not(debug_assertions))]
 const LOG_LEVEL: &str = "info,warn,error,moderation_service=debug";
 
+/// Setting keys are at most 64 characters, `detector_pipeline_` takes 18 of them
+const MAX_API_KEY_ID_LEN: usize = 64 - "detector_pipeline_".len();
+
 lazy_static::lazy_static! {
-    static ref API_KEY: String = std::env::var("API_KEY").unwrap();
+    /// (key id, key) pairs, the id is what gets recorded as the actor of rule changes.
+    /// `API_KEYS=ci:token1,admin:token2` takes precedence over the single `API_KEY`.
+    /// Ids end up in setting keys like `detector_pipeline_<id>`, so they're limited to
+    /// lowercase letters, digits and underscores.
+    static ref API_KEYS: Vec<(String, String)> = match std::env::var("API_KEYS") {
+        Ok(keys) => keys
+            .split(',')
+            .map(|pair| {
+                let (id, key) = pair
+                    .split_once(':')
+                    .expect("API_KEYS entries must look like id:key");
+                let id = id.trim();
+                assert!(
+                    (1..=MAX_API_KEY_ID_LEN).contains(&id.len())
+                        && id.bytes().all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'_')),
+                    "API_KEYS id {id:?} must be 1 to {MAX_API_KEY_ID_LEN} of [a-z0-9_]"
+                );
+                (id.to_string(), key.trim().to_string())
+            })
+            .collect(),
+        Err(_) => vec![("default".to_string(), std::env::var("API_KEY").unwrap())],
+    };
 }
 
 #[tokio::main]
@@ -48,56 +90,84 @@ async fn main() {
         .await
         .expect("failed to connect database");
 
+    // Parsed on first use otherwise, a malformed id should stop the startup
+    lazy_static::initialize(&API_KEYS);
+
     let cache = cache::ModerationCache::new();
 
     // Save bad words, regex rules and settings to cache on startup
-    let bad_words: Vec<models::BadWordRow> = sqlx::query_as("SELECT * FROM bad_words ORDER BY id")
-        .fetch_all(&pool)
+    cache
+        .reload_bad_words(&pool)
         .await
         .expect("bad_words load failed");
 
+    // Compile the regex rules for faster matches and make sure they are valid regexes
     cache
-        .load_bad_words(
-            bad_words
-                .into_iter()
-                .map(|r| (r.word, r.moderation_action.to_string()))
-                .collect(),
-        )
-        .await;
+        .reload_regex_rules(&pool)
+        .await
+        .expect("regex_rules load failed");
 
-    let regex_rules: Vec<models::RegexRuleRow> =
-        sqlx::query_as("SELECT * FROM regex_rules ORDER BY id")
-            .fetch_all(&pool)
-            .await
-            .expect("regex_rules load failed");
+    cache
+        .reload_allow_words(&pool)
+        .await
+        .expect("allow_words load failed");
 
-    // Compile the regex rules for faster matches and make sure they are valid regexes
-    let compiled = regex_rules
-        .into_iter()
-        .map(|r| {
-            let regex: regex::Regex = regex::Regex::new(&r.pattern).expect("invalid regex in DB");
-            (
-                r.id,
-                regex,
-                r.description.unwrap_or_else(|| "Regex kuralı".into()),
-                r.moderation_action.to_string(),
-            )
-        })
-        .collect();
-
-    cache.load_regex_rules(compiled).await;
-
-    let settings: Vec<models::SettingRow> = sqlx::query_as("SELECT * FROM settings ORDER BY key")
-        .fetch_all(&pool)
+    cache
+        .reload_reason_templates(&pool)
         .await
-        .expect("settings load failed");
+        .expect("reason_templates load failed");
+
+    cache
+        .reload_category_thresholds(&pool)
+        .await
+        .expect("category_thresholds load failed");
+
+    // Evaluated on top of the rule hits and the comment's context
+    cache
+        .reload_rule_expressions(&pool)
+        .await
+        .expect("rule_expressions load failed");
+
+    cache
+        .reload_pii_detectors(&pool)
+        .await
+        .expect("pii_detectors load failed");
+
+    cache
+        .reload_domain_rules(&pool)
+        .await
+        .expect("domain_rules load failed");
+
+    cache
+        .reload_reputation_rules(&pool)
+        .await
+        .expect("reputation_rules load failed");
+
+    // Standings persisted before the last shutdown, flushed back periodically
+    cache
+        .reputation
+        .load(&pool)
+        .await
+        .expect("author_reputation load failed");
 
     // Load the settings to cache for future use
     cache
-        .load_settings(settings.into_iter().map(|r| (r.key, r.value)).collect())
-        .await;
+        .reload_settings(&pool)
+        .await
+        .expect("settings load failed");
+
+    // Keep the matchers in sync with the rules' validity windows
+    tokio::spawn(scheduler::run(pool.clone(), cache.clone()));
+    tokio::spawn(reputation::run(pool.clone(), cache.reputation.clone()));
 
-    let ctx = AppContext { pool, cache };
+    // In-process detectors are registered here, `detector_pipeline` settings pick them by name
+    let detectors = Arc::new(with_local_classifier(detectors::Detectors::builtin()));
+
+    let ctx = AppContext {
+        pool,
+        cache,
+        detectors,
+    };
 
     let port = std::env::var("PORT").unwrap_or_else(|_| {
         debug!("PORT not set, using default port 5000");
@@ -123,7 +193,27 @@ async fn main() {
     }
 }
 
-async fn check_auth(req: Request<Body>, next: Next) -> Response {
+/// Adds the model at `LOCAL_CLASSIFIER_MODEL` as the `local_classifier` detector and keeps
+/// reloading it when the file changes
+#[cfg(feature = "local-classifier")]
+fn with_local_classifier(mut detectors: detectors::Detectors) -> detectors::Detectors {
+    if let Ok(path) = std::env::var("LOCAL_CLASSIFIER_MODEL") {
+        let classifier = Arc::new(
+            local_classifier::LocalClassifier::load(path.into())
+                .expect("local classifier model load failed"),
+        );
+        tokio::spawn(local_classifier::watch(classifier.clone()));
+        detectors.register(classifier);
+    }
+    detectors
+}
+
+#[cfg(not(feature = "local-classifier"))]
+fn with_local_classifier(detectors: detectors::Detectors) -> detectors::Detectors {
+    detectors
+}
+
+async fn check_auth(mut req: Request<Body>, next: Next) -> Response {
     //TODO: Add a limit for the unauthorized requests
     let headers = req.headers();
     let get_bearer_token = headers.get("Authorization");
@@ -143,7 +233,9 @@ async fn check_auth(req: Request<Body>, next: Next) -> Response {
             }
         };
 
-        if token == API_KEY.as_str() {
+        if let Some((id, _)) = API_KEYS.iter().find(|(_, key)| key == token) {
+            let id = models::ApiKeyId(id.clone());
+            req.extensions_mut().insert(id);
             return next.run(req).await;
         }
     }
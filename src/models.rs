@@ -1,15 +1,33 @@
+use chrono::{DateTime, Utc};
 use garde::Validate;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use sqlx::FromRow;
 use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+use crate::{expressions::RuleExpr, heuristics::SignalMatch, language, reputation::VerdictEntry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "moderation_action_enum")]
 #[sqlx(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum ModerationAction {
     Approved,
     Rejected,
     NeedsReview,
+    /// Publish the comment with the matched spans masked
+    Redacted,
+}
+
+impl ModerationAction {
+    /// Verdicts only ever escalate, the most severe action among the hits wins
+    pub fn severity(self) -> u8 {
+        match self {
+            ModerationAction::Approved => 0,
+            ModerationAction::Redacted => 1,
+            ModerationAction::NeedsReview => 2,
+            ModerationAction::Rejected => 3,
+        }
+    }
 }
 
 impl fmt::Display for ModerationAction {
@@ -18,20 +36,98 @@ impl fmt::Display for ModerationAction {
             ModerationAction::Approved => write!(f, "APPROVED"),
             ModerationAction::Rejected => write!(f, "REJECTED"),
             ModerationAction::NeedsReview => write!(f, "NEEDS_REVIEW"),
+            ModerationAction::Redacted => write!(f, "REDACTED"),
         }
     }
 }
 
-#[derive(Deserialize, Validate)]
+/// Parses the `Display` form, used for actions stored in `settings`
+impl FromStr for ModerationAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "APPROVED" => Ok(ModerationAction::Approved),
+            "REJECTED" => Ok(ModerationAction::Rejected),
+            "NEEDS_REVIEW" => Ok(ModerationAction::NeedsReview),
+            "REDACTED" => Ok(ModerationAction::Redacted),
+            _ => Err(format!("unknown moderation action: {s}")),
+        }
+    }
+}
+
+/// Shadow rules are evaluated and counted but never change the verdict
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "rule_mode_enum")]
+#[sqlx(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RuleMode {
+    #[default]
+    Enforce,
+    Shadow,
+}
+
+#[derive(Default, Deserialize, Validate)]
 pub struct CommentRequest {
     #[garde(length(min = 1, max = 5000))]
     pub content: String,
+    /// Locale of the reason texts, takes precedence over `Accept-Language`
+    #[garde(pattern(r"^[A-Za-z]{2,3}(-[A-Za-z0-9]{1,8})*$"))]
+    pub locale: Option<String>,
+    /// Context of the comment, rule expressions can condition on it
+    #[garde(length(min = 1, max = 128))]
+    pub author_id: Option<String>,
+    #[garde(range(min = 0))]
+    pub account_age_secs: Option<i64>,
+    #[garde(length(min = 1, max = 128))]
+    pub thread_id: Option<String>,
+    /// Hex digest of the poster's IP, the raw address should never be sent
+    #[garde(pattern(r"^[0-9a-fA-F]{16,128}$"))]
+    pub ip_hash: Option<String>,
+    #[garde(length(max = 32), inner(pattern(r"^[a-z0-9_:-]{1,64}$")))]
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Also returns `moderator_reason`, only for moderator-facing callers
+    #[garde(skip)]
+    #[serde(default)]
+    pub include_moderator_reason: bool,
 }
 
 #[derive(Serialize)]
 pub struct ModerationResponse {
-    pub status: String, // APPROVED | REJECTED | NEEDS_REVIEW
+    /// Serialized through `Display`, i.e. APPROVED | REJECTED | NEEDS_REVIEW | REDACTED
+    #[serde(serialize_with = "serialize_display")]
+    pub status: ModerationAction,
+    /// Stable code of the hit that decided the status, e.g. PROFANITY or PII_PHONE
+    pub reason_code: Option<String>,
+    pub category: Option<String>,
+    /// Distinct codes of every enforced hit, in the order they were found
+    pub reason_codes: Vec<String>,
+    /// Sum of the weights of every enforced hit
+    pub score: i64,
+    /// Points per weighted rule that hit, in the order they were found
+    pub score_breakdown: Vec<ScoreEntry>,
+    /// Heuristic signals that passed their threshold
+    pub signals: Vec<SignalMatch>,
+    /// ISO 639-3 code of the detected language, every rule applies when it's missing
+    pub language: Option<String>,
+    /// Meant for end users, hides the matched word when `hide_matched_word` is set
     pub reason: Option<String>,
+    /// Always names the matched word, only set when the request asks for it with
+    /// `include_moderator_reason`. Never forward it to the comment's author.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub moderator_reason: Option<String>,
+    /// Original content with every redacted span masked, only set when status is REDACTED
+    pub redacted_content: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ScoreEntry {
+    pub rule_kind: RuleKind,
+    pub rule_id: i32,
+    pub reason_code: String,
+    pub category: String,
+    pub hits: u32,
+    pub points: i64,
 }
 
 #[derive(FromRow, Debug, Serialize)]
@@ -39,14 +135,112 @@ pub struct BadWordRow {
     pub id: i32,
     pub word: String,
     pub moderation_action: ModerationAction,
+    pub version: i32,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_until: Option<DateTime<Utc>>,
+    pub enabled: bool,
+    pub mode: RuleMode,
+    pub reason_code: String,
+    pub category: String,
+    pub weight: i32,
+    pub language: Option<String>,
 }
 
-#[derive(Deserialize, Validate)]
+#[derive(Serialize, Deserialize, Validate)]
 pub struct BadWordCreate {
     #[garde(length(min = 2, max = 64))]
     pub word: String,
     #[garde(skip)]
     pub action: ModerationAction,
+    /// Rule is only active inside [valid_from, valid_until), open ended when missing
+    #[garde(skip)]
+    #[serde(default)]
+    pub valid_from: Option<DateTime<Utc>>,
+    #[garde(skip)]
+    #[serde(default)]
+    pub valid_until: Option<DateTime<Utc>>,
+    #[garde(skip)]
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    #[garde(skip)]
+    #[serde(default)]
+    pub mode: RuleMode,
+    #[garde(pattern(r"^[A-Z][A-Z0-9_]{1,63}$"))]
+    #[serde(default = "default_bad_word_code")]
+    pub reason_code: String,
+    #[garde(pattern(r"^[a-z0-9_]{2,64}$"))]
+    #[serde(default = "default_bad_word_category")]
+    pub category: String,
+    /// Points added to the score for every hit, 0 keeps the rule out of scoring
+    #[garde(range(min = 0, max = 1000))]
+    #[serde(default)]
+    pub weight: i32,
+    /// ISO 639-3 code, the rule only applies to comments detected as this language
+    #[garde(custom(known_language))]
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+#[derive(FromRow, Debug, Serialize)]
+pub struct AllowWordRow {
+    pub id: i32,
+    pub word: String,
+    pub version: i32,
+}
+
+/// Exempts bad word hits that fall entirely inside this word, e.g. place names
+#[derive(Serialize, Deserialize, Validate)]
+pub struct AllowWordCreate {
+    #[garde(length(min = 2, max = 64))]
+    pub word: String,
+}
+
+/// Partial update, `version` can be sent here or through the `If-Match` header
+#[derive(Deserialize, Validate)]
+pub struct AllowWordUpdate {
+    #[garde(length(min = 2, max = 64))]
+    pub word: Option<String>,
+    #[garde(skip)]
+    pub version: Option<i32>,
+}
+
+/// Picks one of the rows of a word listed for several languages
+#[derive(Deserialize, Validate)]
+pub struct BadWordLanguage {
+    #[garde(custom(known_language))]
+    pub language: Option<String>,
+}
+
+/// Partial update, `version` can be sent here or through the `If-Match` header
+#[derive(Deserialize, Validate)]
+pub struct BadWordUpdate {
+    #[garde(length(min = 2, max = 64))]
+    pub word: Option<String>,
+    #[garde(skip)]
+    pub action: Option<ModerationAction>,
+    /// `null` clears the bound, a missing field leaves it untouched
+    #[garde(skip)]
+    #[serde(default, deserialize_with = "nullable")]
+    pub valid_from: Option<Option<DateTime<Utc>>>,
+    #[garde(skip)]
+    #[serde(default, deserialize_with = "nullable")]
+    pub valid_until: Option<Option<DateTime<Utc>>>,
+    #[garde(skip)]
+    pub enabled: Option<bool>,
+    #[garde(skip)]
+    pub mode: Option<RuleMode>,
+    #[garde(pattern(r"^[A-Z][A-Z0-9_]{1,63}$"))]
+    pub reason_code: Option<String>,
+    #[garde(pattern(r"^[a-z0-9_]{2,64}$"))]
+    pub category: Option<String>,
+    #[garde(range(min = 0, max = 1000))]
+    pub weight: Option<i32>,
+    /// `null` applies the rule to every language again
+    #[garde(custom(known_language_update))]
+    #[serde(default, deserialize_with = "nullable")]
+    pub language: Option<Option<String>>,
+    #[garde(skip)]
+    pub version: Option<i32>,
 }
 
 #[derive(FromRow, Debug, Serialize)]
@@ -55,9 +249,18 @@ pub struct RegexRuleRow {
     pub pattern: String,
     pub description: Option<String>,
     pub moderation_action: ModerationAction,
+    pub version: i32,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_until: Option<DateTime<Utc>>,
+    pub enabled: bool,
+    pub mode: RuleMode,
+    pub reason_code: String,
+    pub category: String,
+    pub weight: i32,
+    pub language: Option<String>,
 }
 
-#[derive(Deserialize, Validate)]
+#[derive(Serialize, Deserialize, Validate)]
 pub struct RegexRuleCreate {
     #[garde(length(min = 1, max = 512))]
     pub pattern: String,
@@ -65,6 +268,356 @@ pub struct RegexRuleCreate {
     pub description: Option<String>,
     #[garde(skip)]
     pub action: ModerationAction,
+    /// Rule is only active inside [valid_from, valid_until), open ended when missing
+    #[garde(skip)]
+    #[serde(default)]
+    pub valid_from: Option<DateTime<Utc>>,
+    #[garde(skip)]
+    #[serde(default)]
+    pub valid_until: Option<DateTime<Utc>>,
+    #[garde(skip)]
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    #[garde(skip)]
+    #[serde(default)]
+    pub mode: RuleMode,
+    #[garde(pattern(r"^[A-Z][A-Z0-9_]{1,63}$"))]
+    #[serde(default = "default_regex_code")]
+    pub reason_code: String,
+    #[garde(pattern(r"^[a-z0-9_]{2,64}$"))]
+    #[serde(default = "default_regex_category")]
+    pub category: String,
+    /// Points added to the score for every hit, 0 keeps the rule out of scoring
+    #[garde(range(min = 0, max = 1000))]
+    #[serde(default)]
+    pub weight: i32,
+    /// ISO 639-3 code, the rule only applies to comments detected as this language
+    #[garde(custom(known_language))]
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+/// Partial update, `version` can be sent here or through the `If-Match` header
+#[derive(Deserialize, Validate)]
+pub struct RegexRuleUpdate {
+    #[garde(length(min = 1, max = 512))]
+    pub pattern: Option<String>,
+    /// `null` clears the description, a missing field leaves it untouched
+    #[garde(length(min = 0, max = 256))]
+    #[serde(default, deserialize_with = "nullable")]
+    pub description: Option<Option<String>>,
+    #[garde(skip)]
+    pub action: Option<ModerationAction>,
+    /// `null` clears the bound, a missing field leaves it untouched
+    #[garde(skip)]
+    #[serde(default, deserialize_with = "nullable")]
+    pub valid_from: Option<Option<DateTime<Utc>>>,
+    #[garde(skip)]
+    #[serde(default, deserialize_with = "nullable")]
+    pub valid_until: Option<Option<DateTime<Utc>>>,
+    #[garde(skip)]
+    pub enabled: Option<bool>,
+    #[garde(skip)]
+    pub mode: Option<RuleMode>,
+    #[garde(pattern(r"^[A-Z][A-Z0-9_]{1,63}$"))]
+    pub reason_code: Option<String>,
+    #[garde(pattern(r"^[a-z0-9_]{2,64}$"))]
+    pub category: Option<String>,
+    #[garde(range(min = 0, max = 1000))]
+    pub weight: Option<i32>,
+    /// `null` applies the rule to every language again
+    #[garde(custom(known_language_update))]
+    #[serde(default, deserialize_with = "nullable")]
+    pub language: Option<Option<String>>,
+    #[garde(skip)]
+    pub version: Option<i32>,
+}
+
+#[derive(FromRow, Debug, Serialize)]
+pub struct RuleExpressionRow {
+    pub id: i32,
+    pub expression: serde_json::Value,
+    pub description: Option<String>,
+    pub moderation_action: ModerationAction,
+    pub reason_code: String,
+    pub category: String,
+    pub weight: i32,
+    pub enabled: bool,
+    pub mode: RuleMode,
+    pub version: i32,
+}
+
+/// Evaluated on the enforced hits of the rules it references and the comment's context
+#[derive(Deserialize, Validate)]
+pub struct RuleExpressionCreate {
+    #[garde(skip)]
+    pub expression: RuleExpr,
+    #[garde(length(min = 0, max = 256))]
+    pub description: Option<String>,
+    #[garde(custom(maskless_action))]
+    pub action: ModerationAction,
+    #[garde(pattern(r"^[A-Z][A-Z0-9_]{1,63}$"))]
+    #[serde(default = "default_expression_code")]
+    pub reason_code: String,
+    #[garde(pattern(r"^[a-z0-9_]{2,64}$"))]
+    #[serde(default = "default_regex_category")]
+    pub category: String,
+    #[garde(range(min = 0, max = 1000))]
+    #[serde(default)]
+    pub weight: i32,
+    #[garde(skip)]
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    #[garde(skip)]
+    #[serde(default)]
+    pub mode: RuleMode,
+}
+
+/// Partial update, `version` can be sent here or through the `If-Match` header
+#[derive(Deserialize, Validate)]
+pub struct RuleExpressionUpdate {
+    #[garde(skip)]
+    pub expression: Option<RuleExpr>,
+    /// `null` clears the description, a missing field leaves it untouched
+    #[garde(length(min = 0, max = 256))]
+    #[serde(default, deserialize_with = "nullable")]
+    pub description: Option<Option<String>>,
+    #[garde(inner(custom(maskless_action)))]
+    pub action: Option<ModerationAction>,
+    #[garde(pattern(r"^[A-Z][A-Z0-9_]{1,63}$"))]
+    pub reason_code: Option<String>,
+    #[garde(pattern(r"^[a-z0-9_]{2,64}$"))]
+    pub category: Option<String>,
+    #[garde(range(min = 0, max = 1000))]
+    pub weight: Option<i32>,
+    #[garde(skip)]
+    pub enabled: Option<bool>,
+    #[garde(skip)]
+    pub mode: Option<RuleMode>,
+    #[garde(skip)]
+    pub version: Option<i32>,
+}
+
+/// Expressions have no span of their own to mask
+fn maskless_action(action: &ModerationAction, _: &()) -> garde::Result {
+    match action {
+        ModerationAction::Redacted => Err(garde::Error::new(
+            "expressions can't use the REDACTED action",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Rule languages have to be codes the detector can return, a rule for any other code would
+/// never apply
+fn known_language(language: &Option<String>, _: &()) -> garde::Result {
+    match language {
+        Some(code) if !language::is_known(code) => Err(garde::Error::new(
+            "language must be a supported ISO 639-3 code, e.g. tur or eng",
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn known_language_update(language: &Option<Option<String>>, ctx: &()) -> garde::Result {
+    known_language(&language.clone().flatten(), ctx)
+}
+
+/// Built-in personal data detectors, see `pii.rs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "pii_detector_enum")]
+#[sqlx(rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PiiDetector {
+    Email,
+    /// International (E.164) and Turkish phone numbers
+    Phone,
+    Iban,
+    CreditCard,
+    /// T.C. Kimlik No
+    NationalId,
+}
+
+impl PiiDetector {
+    /// Reason code reported for every hit of the detector
+    pub fn reason_code(self) -> &'static str {
+        match self {
+            PiiDetector::Email => "PII_EMAIL",
+            PiiDetector::Phone => "PII_PHONE",
+            PiiDetector::Iban => "PII_IBAN",
+            PiiDetector::CreditCard => "PII_CREDIT_CARD",
+            PiiDetector::NationalId => "PII_NATIONAL_ID",
+        }
+    }
+}
+
+#[derive(FromRow, Debug, Serialize)]
+pub struct PiiDetectorRow {
+    pub id: i32,
+    /// API key id the row applies to, the default of every other key when missing
+    pub tenant: Option<String>,
+    pub detector: PiiDetector,
+    pub moderation_action: ModerationAction,
+    pub weight: i32,
+    pub enabled: bool,
+    pub mode: RuleMode,
+    pub version: i32,
+}
+
+/// The caller's own settings for a detector, missing fields are taken from the default row
+/// on creation and left unchanged on later saves
+#[derive(Deserialize, Validate)]
+pub struct PiiDetectorOverride {
+    #[garde(skip)]
+    pub detector: PiiDetector,
+    #[garde(skip)]
+    pub action: Option<ModerationAction>,
+    #[garde(range(min = 0, max = 1000))]
+    pub weight: Option<i32>,
+    #[garde(skip)]
+    pub enabled: Option<bool>,
+    #[garde(skip)]
+    pub mode: Option<RuleMode>,
+}
+
+/// Partial update, `version` can be sent here or through the `If-Match` header
+#[derive(Deserialize, Validate)]
+pub struct PiiDetectorUpdate {
+    #[garde(skip)]
+    pub action: Option<ModerationAction>,
+    #[garde(range(min = 0, max = 1000))]
+    pub weight: Option<i32>,
+    #[garde(skip)]
+    pub enabled: Option<bool>,
+    #[garde(skip)]
+    pub mode: Option<RuleMode>,
+    #[garde(skip)]
+    pub version: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "domain_list_enum")]
+#[sqlx(rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DomainList {
+    /// Links to the domain never change the verdict, even if a parent domain is denied
+    Allow,
+    Deny,
+}
+
+#[derive(FromRow, Debug, Serialize)]
+pub struct DomainRuleRow {
+    pub id: i32,
+    pub domain: String,
+    pub list: DomainList,
+    /// Only set on DENY entries
+    pub moderation_action: Option<ModerationAction>,
+    pub reason_code: String,
+    pub category: String,
+    pub weight: i32,
+    pub enabled: bool,
+    pub mode: RuleMode,
+    pub version: i32,
+}
+
+/// Matches the domain and all of its subdomains, `action` is required on DENY entries only
+#[derive(Deserialize, Validate)]
+pub struct DomainRuleCreate {
+    /// Stored in punycode, obfuscated dots and a leading `*.` are accepted
+    #[garde(length(min = 1, max = 253))]
+    pub domain: String,
+    #[garde(skip)]
+    pub list: DomainList,
+    #[garde(skip)]
+    pub action: Option<ModerationAction>,
+    #[garde(pattern(r"^[A-Z][A-Z0-9_]{1,63}$"))]
+    #[serde(default = "default_domain_code")]
+    pub reason_code: String,
+    #[garde(pattern(r"^[a-z0-9_]{2,64}$"))]
+    #[serde(default = "default_domain_category")]
+    pub category: String,
+    #[garde(range(min = 0, max = 1000))]
+    #[serde(default)]
+    pub weight: i32,
+    #[garde(skip)]
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    #[garde(skip)]
+    #[serde(default)]
+    pub mode: RuleMode,
+}
+
+/// Partial update, `version` can be sent here or through the `If-Match` header.
+/// Moving an entry to ALLOW clears its action.
+#[derive(Deserialize, Validate)]
+pub struct DomainRuleUpdate {
+    #[garde(length(min = 1, max = 253))]
+    pub domain: Option<String>,
+    #[garde(skip)]
+    pub list: Option<DomainList>,
+    #[garde(skip)]
+    pub action: Option<ModerationAction>,
+    #[garde(pattern(r"^[A-Z][A-Z0-9_]{1,63}$"))]
+    pub reason_code: Option<String>,
+    #[garde(pattern(r"^[a-z0-9_]{2,64}$"))]
+    pub category: Option<String>,
+    #[garde(range(min = 0, max = 1000))]
+    pub weight: Option<i32>,
+    #[garde(skip)]
+    pub enabled: Option<bool>,
+    #[garde(skip)]
+    pub mode: Option<RuleMode>,
+    #[garde(skip)]
+    pub version: Option<i32>,
+}
+
+fn serialize_display<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: fmt::Display,
+{
+    serializer.collect_str(value)
+}
+
+pub fn enabled_by_default() -> bool {
+    true
+}
+
+pub fn default_bad_word_code() -> String {
+    "PROFANITY".to_string()
+}
+
+pub fn default_bad_word_category() -> String {
+    "profanity".to_string()
+}
+
+pub fn default_regex_code() -> String {
+    "REGEX_MATCH".to_string()
+}
+
+pub fn default_regex_category() -> String {
+    "other".to_string()
+}
+
+fn default_expression_code() -> String {
+    "EXPRESSION_MATCH".to_string()
+}
+
+fn default_domain_code() -> String {
+    "SPAM_LINK".to_string()
+}
+
+fn default_domain_category() -> String {
+    "spam".to_string()
+}
+
+/// Tells an explicit `null` apart from a missing field on partial updates
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
 #[derive(FromRow, Debug, Serialize)]
@@ -73,7 +626,7 @@ pub struct SettingRow {
     pub value: String,
 }
 
-#[derive(Deserialize, Validate)]
+#[derive(Serialize, Deserialize, Validate)]
 pub struct SettingInsert {
     #[garde(pattern(r"^[a-z0-9_]{2,64}$"))]
     pub key: String,
@@ -81,9 +634,316 @@ pub struct SettingInsert {
     pub value: String,
 }
 
+/// Which reason a template renders
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "reason_key_enum")]
+#[sqlx(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReasonKey {
+    /// `{word}` is replaced with the matched word
+    BadWord,
+    /// Only used for regex rules without a description
+    Regex,
+    /// `{category}` and `{hits}` are replaced with the category and its hit count
+    CategoryThreshold,
+    /// `{score}` is replaced with the comment's score
+    Score,
+    /// Only used for rule expressions without a description
+    Expression,
+    /// `{detector}` is replaced with the detector that found personal data
+    Pii,
+    /// `{domain}` is replaced with the denied domain
+    Link,
+    /// `{signal}` and `{value}` are replaced with the heuristic signal's code and value
+    Heuristic,
+    /// `{matches}` is replaced with the number of similar recent comments
+    Duplicate,
+    /// `{count}` and `{window}` are replaced with the author's counted verdicts and the window in seconds
+    Reputation,
+    /// `{count}` and `{window}` are replaced with the requests seen and the window in seconds
+    Velocity,
+    /// `{category}` and `{score}` are replaced with the external classifier's category and score
+    Classifier,
+    /// Used for the fallback verdict while the external classifier can't be reached
+    ClassifierUnavailable,
+}
+
+#[derive(FromRow, Debug, Serialize)]
+pub struct ReasonTemplateRow {
+    pub id: i32,
+    pub locale: String,
+    pub reason_key: ReasonKey,
+    pub template: String,
+    pub version: i32,
+}
+
+/// Creates the template or replaces the one of the same locale and key
+#[derive(Serialize, Deserialize, Validate)]
+pub struct ReasonTemplateInsert {
+    #[garde(pattern(r"^[a-z]{2,3}(-[a-z0-9]{1,8})*$"))]
+    pub locale: String,
+    #[garde(skip)]
+    pub reason_key: ReasonKey,
+    #[garde(length(min = 1, max = 256))]
+    pub template: String,
+}
+
+/// Groups reason codes, every bad word and regex rule belongs to exactly one category
+#[derive(FromRow, Debug, Serialize)]
+pub struct CategoryRow {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct CategoryInsert {
+    #[garde(pattern(r"^[a-z0-9_]{2,64}$"))]
+    pub name: String,
+    #[garde(length(min = 0, max = 256))]
+    pub description: Option<String>,
+}
+
+#[derive(FromRow, Debug, Serialize)]
+pub struct CategoryThresholdRow {
+    pub id: i32,
+    pub category: String,
+    pub min_hits: i32,
+    pub moderation_action: ModerationAction,
+    pub version: i32,
+}
+
+/// Creates the threshold or replaces the action of the one with the same category and hit count.
+/// Thresholds only ever escalate, rule actions still apply below them.
+#[derive(Serialize, Deserialize, Validate)]
+pub struct CategoryThresholdInsert {
+    #[garde(pattern(r"^[a-z0-9_]{2,64}$"))]
+    pub category: String,
+    #[garde(range(min = 1))]
+    pub min_hits: i32,
+    #[garde(custom(escalating_action))]
+    pub action: ModerationAction,
+}
+
+fn escalating_action(action: &ModerationAction, _: &()) -> garde::Result {
+    match action {
+        ModerationAction::NeedsReview | ModerationAction::Rejected => Ok(()),
+        _ => Err(garde::Error::new(
+            "threshold action must be NEEDS_REVIEW or REJECTED",
+        )),
+    }
+}
+
+#[derive(FromRow, Debug, Serialize)]
+pub struct ReputationRuleRow {
+    pub id: i32,
+    pub counted_action: ModerationAction,
+    pub min_count: i32,
+    pub window_secs: i32,
+    pub moderation_action: ModerationAction,
+    pub version: i32,
+}
+
+/// Authors with at least `min_count` verdicts of `counted_action` in the last `window_secs`
+/// get their new comments escalated to `action`
+#[derive(Deserialize, Validate)]
+pub struct ReputationRuleInsert {
+    #[garde(skip)]
+    pub counted_action: ModerationAction,
+    #[garde(range(min = 1))]
+    pub min_count: i32,
+    #[garde(range(min = 60, max = 604800))]
+    pub window_secs: i32,
+    #[garde(custom(escalating_action))]
+    pub action: ModerationAction,
+}
+
+#[derive(Serialize)]
+pub struct AuthorStanding {
+    pub author_id: String,
+    /// Reputation rules the author currently trips
+    pub flagged_by: Vec<i32>,
+    /// Verdicts of the last 7 days, oldest first
+    pub verdicts: Vec<VerdictEntry>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct RuleListQuery {
+    #[garde(range(min = 1, max = 500))]
+    pub limit: Option<i64>,
+    /// Opaque cursor returned as `next_cursor` by the previous page
+    #[garde(skip)]
+    pub cursor: Option<String>,
+    /// Case-insensitive substring search
+    #[garde(length(min = 1, max = 128))]
+    pub q: Option<String>,
+    #[garde(skip)]
+    pub action: Option<ModerationAction>,
+    #[garde(length(min = 2, max = 64))]
+    pub category: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+/// Id of the API key that authenticated the request, recorded as the actor of rule changes
+#[derive(Debug, Clone)]
+pub struct ApiKeyId(pub String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "rule_kind_enum")]
+#[sqlx(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RuleKind {
+    BadWord,
+    Regex,
+    Setting,
+    AllowWord,
+    ReasonTemplate,
+    Category,
+    CategoryThreshold,
+    Expression,
+    PiiDetector,
+    DomainRule,
+    ReputationRule,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "rule_operation_enum")]
+#[sqlx(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RuleOperation {
+    Create,
+    Update,
+    Delete,
+    Rollback,
+}
+
+/// `before`/`after` hold the full table row as stored by Postgres, `None` when it did not exist
+#[derive(FromRow, Debug, Serialize)]
+pub struct RuleHistoryRow {
+    pub id: i64,
+    pub rule_kind: RuleKind,
+    pub rule_key: String,
+    pub operation: RuleOperation,
+    pub actor: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct RuleHistoryQuery {
+    #[garde(range(min = 1, max = 500))]
+    pub limit: Option<i64>,
+    /// Opaque cursor returned as `next_cursor` by the previous page
+    #[garde(skip)]
+    pub cursor: Option<String>,
+    #[garde(skip)]
+    pub kind: Option<RuleKind>,
+    #[garde(length(min = 1, max = 64))]
+    pub rule_key: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ShadowHit {
+    pub rule_kind: RuleKind,
+    pub rule_id: i32,
+    pub hits: u64,
+}
+
 #[derive(Serialize)]
 pub struct ApiResponse<T> {
     pub success: bool,
     pub message: String,
     pub data: T,
 }
+
+/// Bumped whenever the shape of [`RuleBundle`] changes in a non-backwards compatible way
+pub const RULE_BUNDLE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct RuleBundle {
+    #[garde(range(min = 1, max = RULE_BUNDLE_VERSION))]
+    pub version: u32,
+    #[garde(dive)]
+    #[serde(default)]
+    pub bad_words: Vec<BadWordCreate>,
+    #[garde(dive)]
+    #[serde(default)]
+    pub regex_rules: Vec<RegexRuleCreate>,
+    #[garde(dive)]
+    #[serde(default)]
+    pub settings: Vec<SettingInsert>,
+    #[garde(dive)]
+    #[serde(default)]
+    pub allow_words: Vec<AllowWordCreate>,
+    /// Imported before the rules so they can reference them
+    #[garde(dive)]
+    #[serde(default)]
+    pub categories: Vec<CategoryInsert>,
+    #[garde(dive)]
+    #[serde(default)]
+    pub category_thresholds: Vec<CategoryThresholdInsert>,
+}
+
+/// Flat row used for the CSV representation of a [`RuleBundle`]
+/// kind: meta | bad_word | regex | setting | allow_word | category | category_threshold
+#[derive(Serialize, Deserialize)]
+pub struct RuleBundleCsvRecord {
+    pub kind: String,
+    pub key: Option<String>,
+    pub value: String,
+    pub action: Option<ModerationAction>,
+    pub description: Option<String>,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_until: Option<DateTime<Utc>>,
+    pub enabled: Option<bool>,
+    pub mode: Option<RuleMode>,
+    pub reason_code: Option<String>,
+    pub category: Option<String>,
+    pub weight: Option<i32>,
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BundleFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Upsert the bundle on top of the existing rules
+    #[default]
+    Merge,
+    /// Drop the existing rules and settings missing from the bundle, rules it has again keep
+    /// their ids
+    Replace,
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: BundleFormat,
+}
+
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    #[serde(default)]
+    pub format: BundleFormat,
+    #[serde(default)]
+    pub mode: ImportMode,
+}
+
+#[derive(Serialize)]
+pub struct ImportSummary {
+    pub bad_words: usize,
+    pub regex_rules: usize,
+    pub settings: usize,
+    pub allow_words: usize,
+    pub categories: usize,
+    pub category_thresholds: usize,
+}
@@ -0,0 +1,316 @@
+use std::collections::BTreeMap;
+
+use crate::expressions::RuleHits;
+
+use crate::{
+    cache::{ModerationCache, RuleCode},
+    detectors::{DetectionContext, Detectors},
+    heuristics::SignalMatch,
+    language,
+    models::{
+        CommentRequest, ModerationAction, ModerationResponse, RuleKind, RuleMode, ScoreEntry,
+    },
+    normalize::{mask_spans, NormalizedText},
+    reasons::{requested_locales, Reason},
+};
+
+const DEFAULT_REDACTION_MASK: &str = "*";
+const CATEGORY_THRESHOLD_CODE: &str = "CATEGORY_THRESHOLD";
+const SCORE_THRESHOLD_CODE: &str = "SCORE_THRESHOLD";
+const REPUTATION_CODE: &str = "REPEAT_OFFENDER";
+const REPUTATION_CATEGORY: &str = "other";
+
+/// Running verdict of a comment, only ever escalates to a more severe action
+struct Verdict {
+    status: ModerationAction,
+    reason: Option<Reason>,
+    code: Option<RuleCode>,
+    /// Distinct reason codes of every enforced hit
+    codes: Vec<String>,
+}
+
+impl Verdict {
+    fn approved() -> Self {
+        Self {
+            status: ModerationAction::Approved,
+            reason: None,
+            code: None,
+            codes: Vec::new(),
+        }
+    }
+
+    /// The first hit of the most severe action keeps its reason
+    fn hit(&mut self, action: ModerationAction, code: &RuleCode, reason: impl FnOnce() -> Reason) {
+        if !self.codes.contains(&code.reason_code) {
+            self.codes.push(code.reason_code.clone());
+        }
+
+        let escalates = action.severity() > self.status.severity();
+        let first_of_kind = action == self.status && self.reason.is_none();
+
+        if escalates || first_of_kind {
+            self.status = action;
+            self.reason = Some(reason());
+            self.code = Some(code.clone());
+        }
+    }
+}
+
+/// Adds the points of `hits` more hits of a weighted rule to its breakdown entry
+fn add_points(
+    breakdown: &mut Vec<ScoreEntry>,
+    rule_kind: RuleKind,
+    rule_id: i32,
+    code: &RuleCode,
+    weight: u32,
+    hits: u32,
+) {
+    if weight == 0 {
+        return;
+    }
+
+    let points = i64::from(weight) * i64::from(hits);
+    match breakdown
+        .iter_mut()
+        .find(|e| e.rule_kind == rule_kind && e.rule_id == rule_id)
+    {
+        Some(entry) => {
+            entry.hits += hits;
+            entry.points += points;
+        }
+        None => breakdown.push(ScoreEntry {
+            rule_kind,
+            rule_id,
+            reason_code: code.reason_code.clone(),
+            category: code.category.clone(),
+            hits,
+            points,
+        }),
+    }
+}
+
+// Check comment here, `tenant` picks the detector pipeline and separates the recent
+// comments of different API keys
+pub async fn moderate_comment(
+    cache: &ModerationCache,
+    detectors: &Detectors,
+    req: &CommentRequest,
+    tenant: &str,
+    accept_language: Option<&str>,
+) -> ModerationResponse {
+    let mask = cache
+        .settings
+        .get("redaction_mask")
+        .await
+        .unwrap_or_else(|| DEFAULT_REDACTION_MASK.to_string());
+
+    // Picks the rule sets below, comments in an unknown language are checked against all of them
+    let language = language::detect(&req.content);
+    let normalized = NormalizedText::new(&req.content);
+    let mut verdict = Verdict::approved();
+    // Spans of every redacting hit, only used if nothing more severe matched
+    let mut redacted_spans: Vec<(usize, usize)> = Vec::new();
+    // Enforced hits per category, sorted so the escalation order doesn't depend on hashing
+    let mut category_hits: BTreeMap<String, u32> = BTreeMap::new();
+    let mut breakdown: Vec<ScoreEntry> = Vec::new();
+    // Enforced hits per rule, what the rule expressions are evaluated on
+    let mut rule_hits = RuleHits::new();
+    let review_score: Option<i64> = cache.parsed_setting("score_review_threshold").await;
+    let reject_score: Option<i64> = cache.parsed_setting("score_reject_threshold").await;
+
+    let ctx = DetectionContext {
+        cache,
+        req,
+        tenant,
+        language,
+    };
+    let mut signals: Vec<SignalMatch> = Vec::new();
+
+    // Keep going after the first enforced hit so shadow rules are always evaluated
+    for detector in detectors.pipeline(cache, tenant).await {
+        for found in detector.evaluate(&normalized, &ctx).await {
+            if found.mode == RuleMode::Shadow {
+                info!(
+                    "Shadow detector match | Detector: {} | Rule: {:?} | Action: {}",
+                    detector.name(),
+                    found.rule,
+                    found.action
+                );
+                if let Some((kind, id)) = found.rule {
+                    cache.record_shadow_hit(kind, id);
+                }
+                continue;
+            }
+
+            if found.action == ModerationAction::Redacted {
+                redacted_spans.extend(
+                    found
+                        .spans
+                        .iter()
+                        .map(|&(start, end)| normalized.original_span(start, end)),
+                );
+            }
+
+            *category_hits
+                .entry(found.code.category.clone())
+                .or_default() += found.hits;
+            if let Some((kind, id)) = found.rule {
+                *rule_hits.entry((kind, id)).or_default() += found.hits;
+                add_points(
+                    &mut breakdown,
+                    kind,
+                    id,
+                    &found.code,
+                    found.weight,
+                    found.hits,
+                );
+            }
+            if let Reason::Heuristic {
+                signal,
+                value,
+                threshold,
+            } = found.reason
+            {
+                signals.push(SignalMatch {
+                    signal,
+                    value,
+                    threshold,
+                });
+            }
+
+            verdict.hit(found.action, &found.code, || found.reason);
+        }
+    }
+
+    if let Some(set) = cache.expression_set.read().unwrap().as_ref() {
+        for idx in 0..set.exprs.len() {
+            if !set.exprs[idx].evaluate(&rule_hits, req) {
+                continue;
+            }
+
+            if set.modes[idx] == RuleMode::Shadow {
+                info!(
+                    "Shadow rule expression matched | Rule: {} | Action: {}",
+                    set.ids[idx], set.actions[idx]
+                );
+                cache.record_shadow_hit(RuleKind::Expression, set.ids[idx]);
+                continue;
+            }
+
+            *category_hits
+                .entry(set.codes[idx].category.clone())
+                .or_default() += 1;
+            add_points(
+                &mut breakdown,
+                RuleKind::Expression,
+                set.ids[idx],
+                &set.codes[idx],
+                set.weights[idx],
+                1,
+            );
+
+            verdict.hit(set.actions[idx], &set.codes[idx], || Reason::Expression {
+                description: set.descriptions[idx].clone(),
+            });
+        }
+    }
+
+    // Categories reaching a threshold escalate on top of the actions of their rules
+    let escalations: Vec<(ModerationAction, String, u32)> = {
+        let thresholds = cache.category_thresholds.read().unwrap();
+        category_hits
+            .into_iter()
+            .filter_map(|(category, hits)| {
+                let &(_, action) = thresholds
+                    .get(&category)?
+                    .iter()
+                    .find(|&&(min_hits, _)| hits >= min_hits)?;
+                Some((action, category, hits))
+            })
+            .collect()
+    };
+    for (action, category, hits) in escalations {
+        let code = RuleCode {
+            reason_code: CATEGORY_THRESHOLD_CODE.to_string(),
+            category: category.clone(),
+        };
+        verdict.hit(action, &code, || Reason::CategoryThreshold {
+            category,
+            hits,
+        });
+    }
+
+    // The score escalates like any other hit, rules with the APPROVED action and a weight
+    // only ever count towards it
+    let score: i64 = breakdown.iter().map(|e| e.points).sum();
+    let score_action = match (reject_score, review_score) {
+        (Some(reject), _) if score >= reject => Some(ModerationAction::Rejected),
+        (_, Some(review)) if score >= review => Some(ModerationAction::NeedsReview),
+        _ => None,
+    };
+    if let Some(action) = score_action {
+        // Attributed to the category that contributed the most points
+        let top = breakdown.iter().max_by_key(|e| e.points);
+        let code = RuleCode {
+            reason_code: SCORE_THRESHOLD_CODE.to_string(),
+            category: top.map(|e| e.category.clone()).unwrap_or_default(),
+        };
+        verdict.hit(action, &code, || Reason::Score { score });
+    }
+
+    if let Some(author) = &req.author_id {
+        // Recorded without the escalation below, otherwise a flagged author would keep
+        // counting their own escalated verdicts
+        let rules = cache.reputation_rules.read().unwrap().clone();
+        let tripped = cache
+            .reputation
+            .assess(&rules, author, verdict.status)
+            .await;
+        for (rule, count) in tripped {
+            let code = RuleCode {
+                reason_code: REPUTATION_CODE.to_string(),
+                category: REPUTATION_CATEGORY.to_string(),
+            };
+            verdict.hit(rule.action, &code, || Reason::Reputation {
+                count,
+                window_secs: rule.window_secs,
+            });
+        }
+    }
+
+    let redacted_content = match verdict.status {
+        ModerationAction::Redacted => Some(mask_spans(&req.content, redacted_spans, &mask)),
+        _ => None,
+    };
+
+    let (reason, moderator_reason) = match &verdict.reason {
+        Some(reason) => {
+            let locales = requested_locales(req.locale.as_deref(), accept_language);
+            let hide_word =
+                cache.settings.get("hide_matched_word").await.as_deref() == Some("true");
+            let rendered = reason.render(cache, &locales, hide_word, &mask).await;
+            let moderator = req.include_moderator_reason.then_some(rendered.moderator);
+            (Some(rendered.public), moderator)
+        }
+        None => (None, None),
+    };
+
+    let (reason_code, category) = match verdict.code {
+        Some(code) => (Some(code.reason_code), Some(code.category)),
+        None => (None, None),
+    };
+
+    ModerationResponse {
+        status: verdict.status,
+        reason_code,
+        category,
+        reason_codes: verdict.codes,
+        score,
+        score_breakdown: breakdown,
+        signals,
+        language: language.map(str::to_string),
+        reason,
+        moderator_reason,
+        redacted_content,
+    }
+}
@@ -0,0 +1,155 @@
+/// Lowercased copy of a text that remembers where every byte came from. Full-width forms
+/// are folded to ASCII and zero-width characters dropped, so `ｆｏｏ` and `f\u{200b}oo` read as
+/// `foo`. Both that and lowercasing change byte lengths (e.g. `İ` becomes `i̇`), so spans
+/// found on the normalized text can't be used on the original one directly.
+pub struct NormalizedText {
+    pub text: String,
+    /// Byte range in the original text of the character each normalized byte belongs to
+    origins: Vec<(usize, usize)>,
+}
+
+impl NormalizedText {
+    pub fn new(original: &str) -> Self {
+        let mut text = String::with_capacity(original.len());
+        let mut origins = Vec::with_capacity(original.len());
+
+        for (start, c) in original.char_indices() {
+            if is_invisible(c) {
+                continue;
+            }
+            let end = start + c.len_utf8();
+            for lower in fold_width(c).to_lowercase() {
+                text.push(lower);
+                origins.extend(std::iter::repeat_n((start, end), lower.len_utf8()));
+            }
+        }
+
+        Self { text, origins }
+    }
+
+    /// Maps a byte span of the normalized text back to the original text
+    pub fn original_span(&self, start: usize, end: usize) -> (usize, usize) {
+        debug_assert!(start < end && end <= self.origins.len());
+        (self.origins[start].0, self.origins[end - 1].1)
+    }
+}
+
+/// Zero-width characters and the soft hyphen, they split words without showing
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00ad}' | '\u{200b}'..='\u{200d}' | '\u{2060}' | '\u{feff}'
+    )
+}
+
+/// Full-width ASCII variants and the ideographic space to their ASCII counterparts
+fn fold_width(c: char) -> char {
+    match c {
+        '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xfee0).unwrap_or(c),
+        '\u{3000}' => ' ',
+        _ => c,
+    }
+}
+
+/// Replaces the given spans of `original`. A single character mask is repeated for every
+/// masked character, longer masks replace the whole span once.
+pub fn mask_spans(original: &str, mut spans: Vec<(usize, usize)>, mask: &str) -> String {
+    spans.sort_unstable();
+
+    let mut out = String::with_capacity(original.len());
+    let mut cursor = 0;
+    let single_char = mask.chars().count() == 1;
+
+    for (start, end) in spans {
+        // Overlapping spans were already masked by the previous one
+        let start = start.max(cursor);
+        if start >= end {
+            continue;
+        }
+
+        out.push_str(&original[cursor..start]);
+        if single_char {
+            for _ in original[start..end].chars() {
+                out.push_str(mask);
+            }
+        } else {
+            out.push_str(mask);
+        }
+        cursor = end;
+    }
+
+    out.push_str(&original[cursor..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Original text behind the first occurrence of `needle` in the normalized text
+    fn original<'a>(original: &'a str, needle: &str) -> &'a str {
+        let normalized = NormalizedText::new(original);
+        let start = normalized.text.find(needle).unwrap();
+        let (start, end) = normalized.original_span(start, start + needle.len());
+        &original[start..end]
+    }
+
+    #[test]
+    fn lowercases() {
+        assert_eq!(NormalizedText::new("HeLLo ÇĞÖŞÜ").text, "hello çğöşü");
+        // Dotted capital I lowercases to i followed by a combining dot
+        assert_eq!(NormalizedText::new("İ").text, "i\u{307}");
+    }
+
+    #[test]
+    fn folds_full_width_and_drops_zero_width() {
+        assert_eq!(NormalizedText::new("ＦＯＯ！").text, "foo!");
+        assert_eq!(NormalizedText::new("a\u{3000}b").text, "a b");
+        assert_eq!(
+            NormalizedText::new("f\u{200b}o\u{200d}o\u{00ad}x\u{feff}").text,
+            "foox"
+        );
+    }
+
+    #[test]
+    fn spans_map_back_across_length_changes() {
+        // `i̇` is three bytes for the two of `İ`
+        assert_eq!(original("İSTANBUL köpek", "köpek"), "köpek");
+        assert_eq!(original("İdiot", "i\u{307}diot"), "İdiot");
+        assert_eq!(original("bu ＦＯＯ değil", "foo"), "ＦＯＯ");
+        assert_eq!(
+            original("x f\u{200b}o\u{200b}o y", "foo"),
+            "f\u{200b}o\u{200b}o"
+        );
+        assert_eq!(original("ẞ STRASSE", "strasse"), "STRASSE");
+    }
+
+    #[test]
+    fn masks_every_character_with_a_single_char_mask() {
+        let text = "bu İdiot ＦＯＯ";
+        let normalized = NormalizedText::new(text);
+        let spans = ["i\u{307}diot", "foo"]
+            .iter()
+            .map(|needle| {
+                let start = normalized.text.find(needle).unwrap();
+                normalized.original_span(start, start + needle.len())
+            })
+            .collect();
+
+        assert_eq!(mask_spans(text, spans, "*"), "bu ***** ***");
+    }
+
+    #[test]
+    fn longer_masks_replace_the_span_once() {
+        assert_eq!(mask_spans("a bad word", vec![(2, 5)], "[x]"), "a [x] word");
+    }
+
+    #[test]
+    fn overlapping_spans_are_masked_once() {
+        assert_eq!(
+            mask_spans("abcdefgh", vec![(4, 7), (1, 3), (2, 5)], "*"),
+            "a******h"
+        );
+        assert_eq!(mask_spans("abcdef", vec![(1, 5), (2, 3)], "#"), "a####f");
+    }
+}
@@ -0,0 +1,244 @@
+use regex::Regex;
+
+use crate::models::PiiDetector;
+
+lazy_static::lazy_static! {
+    static ref EMAIL: Regex =
+        Regex::new(r"[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}").unwrap();
+    /// Digits with at most two spaces, dots, dashes or parentheses between them
+    static ref PHONE: Regex = Regex::new(r"(?:\+|\b)\(?\d(?:[ ().-]{0,2}\d){6,}\b").unwrap();
+    static ref IBAN: Regex = Regex::new(r"\b[a-z]{2}\d{2}(?:\s?[a-z0-9]){11,30}\b").unwrap();
+    static ref CREDIT_CARD: Regex = Regex::new(r"\b\d(?:[ -]?\d){12,18}\b").unwrap();
+    static ref NATIONAL_ID: Regex = Regex::new(r"\b[1-9]\d{10}\b").unwrap();
+}
+
+/// Byte spans of the values the detector finds in lowercased text. A candidate can run into
+/// the words or numbers after it, e.g. a year after a phone number, so it's cut back to its
+/// longest valid prefix ending at a word boundary.
+pub fn detect(text: &str, detector: PiiDetector) -> Vec<(usize, usize)> {
+    let (re, valid): (&Regex, fn(&str) -> bool) = match detector {
+        PiiDetector::Email => (&EMAIL, |_| true),
+        PiiDetector::Phone => (&PHONE, is_phone),
+        PiiDetector::Iban => (&IBAN, is_iban),
+        PiiDetector::CreditCard => (&CREDIT_CARD, is_credit_card),
+        PiiDetector::NationalId => (&NATIONAL_ID, is_tc_kimlik),
+    };
+
+    let mut spans = Vec::new();
+    let mut at = 0;
+    while let Some(m) = re.find_at(text, at) {
+        let candidate = m.as_str();
+        match word_ends(candidate)
+            .into_iter()
+            .rev()
+            .find(|&end| valid(&candidate[..end]))
+        {
+            Some(end) => {
+                spans.push((m.start(), m.start() + end));
+                at = m.start() + end;
+            }
+            // The value may start at a later word, e.g. after a year
+            None => at = next_word(candidate).map_or(m.end(), |i| m.start() + i),
+        }
+    }
+
+    spans
+}
+
+/// Byte offsets where a word of `candidate` ends
+fn word_ends(candidate: &str) -> Vec<usize> {
+    let mut ends = Vec::new();
+    let mut chars = candidate.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let joined = chars
+            .peek()
+            .is_some_and(|&(_, next)| next.is_alphanumeric());
+        if c.is_alphanumeric() && !joined {
+            ends.push(i + c.len_utf8());
+        }
+    }
+    ends
+}
+
+/// Byte offset of the second word of `candidate`
+fn next_word(candidate: &str) -> Option<usize> {
+    let mut after_separator = false;
+    for (i, c) in candidate.char_indices() {
+        if c.is_alphanumeric() {
+            if after_separator {
+                return Some(i);
+            }
+        } else if i > 0 {
+            after_separator = true;
+        }
+    }
+    None
+}
+
+fn digits(value: &str) -> Vec<u32> {
+    value.chars().filter_map(|c| c.to_digit(10)).collect()
+}
+
+/// International numbers need a leading `+` and 8 to 15 digits (E.164). Turkish ones are
+/// 10 digits after a 0 or 90 prefix with an area or operator code starting with 2-5, without
+/// the prefix only mobile numbers (5xx) count so dates and amounts don't look like landlines.
+fn is_phone(value: &str) -> bool {
+    let d = digits(value);
+
+    if value.starts_with('+') && (8..=15).contains(&d.len()) {
+        return true;
+    }
+
+    match d.as_slice() {
+        [9, 0, first, rest @ ..] | [0, first, rest @ ..] if rest.len() == 9 => {
+            (2..=5).contains(first)
+        }
+        [first, rest @ ..] if rest.len() == 9 => *first == 5,
+        _ => false,
+    }
+}
+
+/// ISO 13616 mod-97 check over the country code, check digits and account number
+fn is_iban(value: &str) -> bool {
+    let compact: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase();
+    if !(15..=34).contains(&compact.len()) {
+        return false;
+    }
+
+    let (head, tail) = compact.split_at(4);
+    let mut remainder: u32 = 0;
+    for c in tail.chars().chain(head.chars()) {
+        let Some(value) = c.to_digit(36) else {
+            return false;
+        };
+        // Letters expand to two digits, A = 10 ... Z = 35
+        remainder = if value >= 10 {
+            (remainder * 100 + value) % 97
+        } else {
+            (remainder * 10 + value) % 97
+        };
+    }
+
+    remainder == 1
+}
+
+fn is_credit_card(value: &str) -> bool {
+    let d = digits(value);
+    if !(13..=19).contains(&d.len()) {
+        return false;
+    }
+
+    let sum: u32 = d
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| match i % 2 {
+            0 => digit,
+            _ if digit * 2 > 9 => digit * 2 - 9,
+            _ => digit * 2,
+        })
+        .sum();
+
+    sum.is_multiple_of(10)
+}
+
+/// T.C. Kimlik No: the 10th digit is derived from the first nine, the 11th from the first ten
+fn is_tc_kimlik(value: &str) -> bool {
+    let d = digits(value);
+    if d.len() != 11 || d[0] == 0 {
+        return false;
+    }
+
+    let odd: u32 = d[0..9].iter().step_by(2).sum();
+    let even: u32 = d[1..8].iter().step_by(2).sum();
+    // +40 keeps the subtraction unsigned, `even` is at most 4 * 9
+    let tenth = (odd * 7 + 40 - even) % 10;
+    let eleventh = d[..10].iter().sum::<u32>() % 10;
+
+    d[9] == tenth && d[10] == eleventh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iban_checksum() {
+        assert!(is_iban("tr33 0006 1005 1978 6457 8413 26"));
+        assert!(is_iban("DE89370400440532013000"));
+        assert!(is_iban("gb82 west 1234 5698 7654 32"));
+        assert!(!is_iban("tr34 0006 1005 1978 6457 8413 26"));
+        assert!(!is_iban("tr33 0006 1005 1978 6457 8413 26 adresine"));
+        assert!(!is_iban("de89 3704"));
+    }
+
+    #[test]
+    fn credit_card_luhn() {
+        assert!(is_credit_card("4111 1111 1111 1111"));
+        assert!(is_credit_card("5500-0000-0000-0004"));
+        assert!(is_credit_card("378282246310005"));
+        assert!(!is_credit_card("4111 1111 1111 1112"));
+        assert!(!is_credit_card("4111 1111 1111"));
+    }
+
+    #[test]
+    fn tc_kimlik_check_digits() {
+        assert!(is_tc_kimlik("10000000146"));
+        assert!(!is_tc_kimlik("10000000147"));
+        assert!(!is_tc_kimlik("10000000156"));
+        assert!(!is_tc_kimlik("01000000146"));
+        assert!(!is_tc_kimlik("1000000014"));
+    }
+
+    #[test]
+    fn phone_numbers() {
+        assert!(is_phone("+90 532 123 45 67"));
+        assert!(is_phone("+1 (415) 555-2671"));
+        assert!(is_phone("0532 123 45 67"));
+        assert!(is_phone("0 (212) 123 45 67"));
+        assert!(is_phone("532 123 45 67"));
+        // Landlines need the prefix, dates and amounts look like them otherwise
+        assert!(!is_phone("212 123 45 67"));
+        assert!(!is_phone("0632 123 45 67"));
+        assert!(!is_phone("1234567"));
+    }
+
+    fn found(text: &str, detector: PiiDetector) -> Vec<&str> {
+        detect(text, detector)
+            .into_iter()
+            .map(|(start, end)| &text[start..end])
+            .collect()
+    }
+
+    #[test]
+    fn iban_stops_before_following_words() {
+        assert_eq!(
+            found(
+                "iban: tr33 0006 1005 1978 6457 8413 26 adresine gonder",
+                PiiDetector::Iban
+            ),
+            ["tr33 0006 1005 1978 6457 8413 26"]
+        );
+    }
+
+    #[test]
+    fn phone_stops_at_adjacent_numbers() {
+        assert_eq!(
+            found("ara 0532 123 45 67 2024", PiiDetector::Phone),
+            ["0532 123 45 67"]
+        );
+        assert_eq!(
+            found("2024 0532 123 45 67", PiiDetector::Phone),
+            ["0532 123 45 67"]
+        );
+        assert_eq!(
+            found("0532 123 45 67 0533 765 43 21", PiiDetector::Phone),
+            ["0532 123 45 67", "0533 765 43 21"]
+        );
+        assert!(found("1999-2024 arasi", PiiDetector::Phone).is_empty());
+    }
+}
@@ -0,0 +1,325 @@
+use crate::{
+    cache::ModerationCache,
+    heuristics::Signal,
+    models::{PiiDetector, ReasonKey},
+    normalize::mask_spans,
+};
+
+/// Used when neither the request nor the `default_locale` setting names a known locale
+const FALLBACK_LOCALE: &str = "tr";
+
+/// What made a comment hit a rule, rendered into text only once the verdict is final
+pub enum Reason {
+    BadWord {
+        word: String,
+    },
+    Regex {
+        description: Option<String>,
+    },
+    CategoryThreshold {
+        category: String,
+        hits: u32,
+    },
+    Score {
+        score: i64,
+    },
+    Expression {
+        description: Option<String>,
+    },
+    Pii {
+        detector: PiiDetector,
+    },
+    Link {
+        domain: String,
+    },
+    Heuristic {
+        signal: Signal,
+        value: u32,
+        threshold: u32,
+    },
+    Duplicate {
+        matches: usize,
+    },
+    Reputation {
+        count: usize,
+        window_secs: i32,
+    },
+    Velocity {
+        count: usize,
+        window_secs: u64,
+    },
+    Classifier {
+        category: String,
+        score: f64,
+    },
+    ClassifierUnavailable,
+}
+
+/// Rendered reason texts of a verdict
+pub struct RenderedReason {
+    pub public: String,
+    pub moderator: String,
+}
+
+/// Locales to try in order: the explicit `locale` field, then `Accept-Language` by quality.
+/// Region tags are followed by their primary language, e.g. `en-us` then `en`.
+pub fn requested_locales(explicit: Option<&str>, accept_language: Option<&str>) -> Vec<String> {
+    let mut weighted: Vec<(&str, f32)> = accept_language
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let tag = parts.next()?.trim();
+            let q = parts
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (!tag.is_empty() && tag != "*" && q > 0.0).then_some((tag, q))
+        })
+        .collect();
+    // Stable, so equally weighted tags keep the client's order
+    weighted.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut locales: Vec<String> = Vec::new();
+    for tag in explicit
+        .into_iter()
+        .chain(weighted.into_iter().map(|(t, _)| t))
+    {
+        let tag = tag.to_lowercase();
+        let primary = tag.split('-').next().unwrap_or_default().to_string();
+        for candidate in [tag, primary] {
+            if !locales.contains(&candidate) {
+                locales.push(candidate);
+            }
+        }
+    }
+
+    locales
+}
+
+impl Reason {
+    fn key(&self) -> ReasonKey {
+        match self {
+            Reason::BadWord { .. } => ReasonKey::BadWord,
+            Reason::Regex { .. } => ReasonKey::Regex,
+            Reason::CategoryThreshold { .. } => ReasonKey::CategoryThreshold,
+            Reason::Score { .. } => ReasonKey::Score,
+            Reason::Expression { .. } => ReasonKey::Expression,
+            Reason::Pii { .. } => ReasonKey::Pii,
+            Reason::Link { .. } => ReasonKey::Link,
+            Reason::Heuristic { .. } => ReasonKey::Heuristic,
+            Reason::Duplicate { .. } => ReasonKey::Duplicate,
+            Reason::Reputation { .. } => ReasonKey::Reputation,
+            Reason::Velocity { .. } => ReasonKey::Velocity,
+            Reason::Classifier { .. } => ReasonKey::Classifier,
+            Reason::ClassifierUnavailable => ReasonKey::ClassifierUnavailable,
+        }
+    }
+
+    /// Renders the reason with the first template found among `locales`,
+    /// `hide_word` masks the matched word in the public text only
+    pub async fn render(
+        &self,
+        cache: &ModerationCache,
+        locales: &[String],
+        hide_word: bool,
+        mask: &str,
+    ) -> RenderedReason {
+        let template = match self {
+            // Descriptions are written per rule and aren't localized
+            Reason::Regex {
+                description: Some(description),
+            }
+            | Reason::Expression {
+                description: Some(description),
+            } => description.clone(),
+            _ => self.template(cache, locales).await,
+        };
+
+        match self {
+            Reason::BadWord { word } => {
+                let shown = if hide_word {
+                    mask_spans(word, vec![(0, word.len())], mask)
+                } else {
+                    word.clone()
+                };
+                RenderedReason {
+                    public: template.replace("{word}", &shown),
+                    moderator: template.replace("{word}", word),
+                }
+            }
+            Reason::Regex { .. } | Reason::Expression { .. } | Reason::ClassifierUnavailable => {
+                RenderedReason {
+                    public: template.clone(),
+                    moderator: template,
+                }
+            }
+            Reason::CategoryThreshold { category, hits } => {
+                let text = template
+                    .replace("{category}", category)
+                    .replace("{hits}", &hits.to_string());
+                RenderedReason {
+                    public: text.clone(),
+                    moderator: text,
+                }
+            }
+            Reason::Pii { detector } => {
+                let text = template.replace("{detector}", detector.reason_code());
+                RenderedReason {
+                    public: text.clone(),
+                    moderator: text,
+                }
+            }
+            Reason::Link { domain } => {
+                let text = template.replace("{domain}", domain);
+                RenderedReason {
+                    public: text.clone(),
+                    moderator: text,
+                }
+            }
+            Reason::Heuristic { signal, value, .. } => {
+                let text = template
+                    .replace("{signal}", signal.reason_code())
+                    .replace("{value}", &value.to_string());
+                RenderedReason {
+                    public: text.clone(),
+                    moderator: text,
+                }
+            }
+            Reason::Duplicate { matches } => {
+                let text = template.replace("{matches}", &matches.to_string());
+                RenderedReason {
+                    public: text.clone(),
+                    moderator: text,
+                }
+            }
+            Reason::Reputation { count, window_secs } => {
+                let text = template
+                    .replace("{count}", &count.to_string())
+                    .replace("{window}", &window_secs.to_string());
+                RenderedReason {
+                    public: text.clone(),
+                    moderator: text,
+                }
+            }
+            Reason::Velocity { count, window_secs } => {
+                let text = template
+                    .replace("{count}", &count.to_string())
+                    .replace("{window}", &window_secs.to_string());
+                RenderedReason {
+                    public: text.clone(),
+                    moderator: text,
+                }
+            }
+            Reason::Classifier { category, score } => {
+                let text = template
+                    .replace("{category}", category)
+                    .replace("{score}", &format!("{score:.2}"));
+                RenderedReason {
+                    public: text.clone(),
+                    moderator: text,
+                }
+            }
+            Reason::Score { score } => {
+                let text = template.replace("{score}", &score.to_string());
+                RenderedReason {
+                    public: text.clone(),
+                    moderator: text,
+                }
+            }
+        }
+    }
+
+    async fn template(&self, cache: &ModerationCache, locales: &[String]) -> String {
+        let key = self.key();
+        let default_locale = cache.settings.get("default_locale").await;
+
+        let candidates = locales
+            .iter()
+            .map(String::as_str)
+            .chain(default_locale.as_deref())
+            .chain([FALLBACK_LOCALE]);
+        for locale in candidates {
+            if let Some(template) = cache.reason_templates.get(&(locale.to_string(), key)).await {
+                return template;
+            }
+        }
+
+        // Only reached when the templates table was emptied
+        match key {
+            ReasonKey::BadWord => "Küfür tespit edildi: {word}".to_string(),
+            ReasonKey::Regex => "Regex kuralı".to_string(),
+            ReasonKey::CategoryThreshold => {
+                "{category} kategorisinde {hits} ihlal tespit edildi".to_string()
+            }
+            ReasonKey::Score => "Yorum puanı eşiği aştı: {score}".to_string(),
+            ReasonKey::Expression => "Kural kombinasyonu eşleşti".to_string(),
+            ReasonKey::Pii => "Kişisel veri tespit edildi: {detector}".to_string(),
+            ReasonKey::Link => "Yasaklı bağlantı tespit edildi: {domain}".to_string(),
+            ReasonKey::Heuristic => "Şüpheli içerik sinyali: {signal} ({value})".to_string(),
+            ReasonKey::Duplicate => "Son yorumlardan {matches} tanesine çok benziyor".to_string(),
+            ReasonKey::Reputation => "Yazarın son {window} saniyede {count} ihlali var".to_string(),
+            ReasonKey::Velocity => "Son {window} saniyede çok fazla yorum: {count}".to_string(),
+            ReasonKey::Classifier => "Sınıflandırıcı {category} puanı: {score}".to_string(),
+            ReasonKey::ClassifierUnavailable => "İçerik sınıflandırıcısına ulaşılamadı".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bad_word() -> Reason {
+        Reason::BadWord {
+            word: "kötü".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn public_reason_masks_the_word_when_hidden() {
+        let cache = ModerationCache::new();
+        let locales = requested_locales(Some("tr"), None);
+
+        let rendered = bad_word().render(&cache, &locales, true, "*").await;
+        assert_eq!(rendered.public, "Küfür tespit edildi: ****");
+        assert_eq!(rendered.moderator, "Küfür tespit edildi: kötü");
+
+        let rendered = bad_word().render(&cache, &locales, true, "[x]").await;
+        assert_eq!(rendered.public, "Küfür tespit edildi: [x]");
+    }
+
+    #[tokio::test]
+    async fn public_reason_names_the_word_unless_hidden() {
+        let cache = ModerationCache::new();
+        let locales = requested_locales(Some("tr"), None);
+
+        let rendered = bad_word().render(&cache, &locales, false, "*").await;
+        assert_eq!(rendered.public, "Küfür tespit edildi: kötü");
+        assert_eq!(rendered.public, rendered.moderator);
+    }
+
+    #[tokio::test]
+    async fn other_reasons_render_the_same_for_both() {
+        let cache = ModerationCache::new();
+        let reason = Reason::Link {
+            domain: "spam.example".to_string(),
+        };
+
+        let rendered = reason.render(&cache, &[], true, "*").await;
+        assert_eq!(
+            rendered.public,
+            "Yasaklı bağlantı tespit edildi: spam.example"
+        );
+        assert_eq!(rendered.public, rendered.moderator);
+    }
+
+    #[test]
+    fn locales_follow_the_explicit_field_then_quality() {
+        assert_eq!(
+            requested_locales(Some("en-US"), Some("de;q=0.5, fr-CA, *")),
+            ["en-us", "en", "fr-ca", "fr", "de"]
+        );
+        assert!(requested_locales(None, Some("en;q=0")).is_empty());
+    }
+}
@@ -0,0 +1,337 @@
+use chrono::{DateTime, Utc};
+use moka::future::Cache;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{cache::ReputationRule, errors::Error, models::ModerationAction};
+
+/// Longest window a reputation rule can look back, older verdicts are dropped
+pub const MAX_WINDOW: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const MAX_VERDICTS_PER_AUTHOR: usize = 1_000;
+const MAX_AUTHORS: u64 = 1_000_000;
+/// How often changed standings are written to `author_reputation`
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct VerdictEntry {
+    pub at: DateTime<Utc>,
+    pub status: ModerationAction,
+}
+
+#[derive(Default)]
+struct Standing {
+    /// Oldest first
+    verdicts: VecDeque<VerdictEntry>,
+    /// Changed since the last flush
+    dirty: bool,
+}
+
+impl Standing {
+    fn prune(&mut self, now: DateTime<Utc>) {
+        while self
+            .verdicts
+            .front()
+            .is_some_and(|v| (now - v.at).to_std().unwrap_or_default() > MAX_WINDOW)
+        {
+            self.verdicts.pop_front();
+        }
+    }
+}
+
+/// Verdicts per author id over the last [`MAX_WINDOW`]
+#[derive(Clone)]
+pub struct Reputation {
+    authors: Cache<String, Arc<Mutex<Standing>>>,
+}
+
+impl Reputation {
+    pub fn new() -> Self {
+        Self {
+            authors: Cache::builder()
+                .max_capacity(MAX_AUTHORS)
+                .time_to_idle(MAX_WINDOW)
+                .build(),
+        }
+    }
+
+    pub async fn record(&self, author: &str, status: ModerationAction) {
+        let standing = self
+            .authors
+            .get_with(author.to_string(), async { Default::default() })
+            .await;
+        let mut standing = standing.lock().unwrap();
+
+        let now = Utc::now();
+        standing.prune(now);
+        if standing.verdicts.len() == MAX_VERDICTS_PER_AUTHOR {
+            standing.verdicts.pop_front();
+        }
+        standing
+            .verdicts
+            .push_back(VerdictEntry { at: now, status });
+        standing.dirty = true;
+    }
+
+    /// Rules tripped by the author's earlier verdicts, then records `status`. Callers pass
+    /// the verdict before any reputation escalation, otherwise a flagged author would keep
+    /// counting their own escalated verdicts
+    pub async fn assess(
+        &self,
+        rules: &[ReputationRule],
+        author: &str,
+        status: ModerationAction,
+    ) -> Vec<(ReputationRule, usize)> {
+        let verdicts = self.verdicts(author).await;
+        let tripped = tripped(rules, &verdicts, Utc::now())
+            .into_iter()
+            .map(|(rule, count)| (rule.clone(), count))
+            .collect();
+        self.record(author, status).await;
+        tripped
+    }
+
+    /// Verdicts of the author still inside [`MAX_WINDOW`], oldest first
+    pub async fn verdicts(&self, author: &str) -> Vec<VerdictEntry> {
+        let Some(standing) = self.authors.get(author).await else {
+            return Vec::new();
+        };
+        let mut standing = standing.lock().unwrap();
+
+        standing.prune(Utc::now());
+        standing.verdicts.iter().copied().collect()
+    }
+
+    /// Forgets the author's verdicts, in memory and in the database
+    pub async fn reset(&self, pool: &PgPool, author: &str) -> Result<(), Error> {
+        self.authors.invalidate(author).await;
+
+        sqlx::query("DELETE FROM author_reputation WHERE author_id = $1")
+            .bind(author)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Restores the standings written by the last flushes, called once on startup
+    pub async fn load(&self, pool: &PgPool) -> Result<(), Error> {
+        let rows: Vec<(String, sqlx::types::Json<Vec<VerdictEntry>>)> = sqlx::query_as(
+            "SELECT author_id, verdicts FROM author_reputation
+             WHERE updated_at > now() - make_interval(secs => $1)",
+        )
+        .bind(MAX_WINDOW.as_secs_f64())
+        .fetch_all(pool)
+        .await?;
+
+        debug!(
+            "Loading author reputation into cache | Authors Loaded: {}",
+            rows.len()
+        );
+
+        let now = Utc::now();
+        for (author, verdicts) in rows {
+            let mut standing = Standing {
+                verdicts: verdicts.0.into(),
+                dirty: false,
+            };
+            standing.prune(now);
+            self.authors
+                .insert(author, Arc::new(Mutex::new(standing)))
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Writes every standing that changed since the last flush and drops expired rows
+    pub async fn flush(&self, pool: &PgPool) -> Result<(), Error> {
+        let mut changed = Vec::new();
+        for (author, standing) in self.authors.iter() {
+            let mut standing = standing.lock().unwrap();
+            if standing.dirty {
+                standing.dirty = false;
+                let verdicts: Vec<VerdictEntry> = standing.verdicts.iter().copied().collect();
+                changed.push((author.to_string(), verdicts));
+            }
+        }
+
+        for (i, (author, verdicts)) in changed.iter().enumerate() {
+            let written = sqlx::query(
+                "INSERT INTO author_reputation (author_id, verdicts) VALUES ($1, $2)
+                 ON CONFLICT (author_id) DO UPDATE
+                 SET verdicts = EXCLUDED.verdicts, updated_at = now()",
+            )
+            .bind(author)
+            .bind(sqlx::types::Json(verdicts))
+            .execute(pool)
+            .await;
+
+            if let Err(e) = written {
+                // Retried on the next flush
+                for (author, _) in &changed[i..] {
+                    if let Some(standing) = self.authors.get(author).await {
+                        standing.lock().unwrap().dirty = true;
+                    }
+                }
+                return Err(e.into());
+            }
+        }
+
+        sqlx::query(
+            "DELETE FROM author_reputation WHERE updated_at <= now() - make_interval(secs => $1)",
+        )
+        .bind(MAX_WINDOW.as_secs_f64())
+        .execute(pool)
+        .await?;
+
+        debug!(
+            "Flushed author reputation | Authors Written: {}",
+            changed.len()
+        );
+
+        Ok(())
+    }
+}
+
+/// Rules tripped by the verdicts, each with the number of verdicts it counted
+pub fn tripped<'a>(
+    rules: &'a [ReputationRule],
+    verdicts: &[VerdictEntry],
+    now: DateTime<Utc>,
+) -> Vec<(&'a ReputationRule, usize)> {
+    rules
+        .iter()
+        .filter_map(|rule| {
+            let since = now - chrono::Duration::seconds(i64::from(rule.window_secs));
+            let count = verdicts
+                .iter()
+                .filter(|v| v.status == rule.counted_action && v.at > since)
+                .count();
+            (count >= rule.min_count).then_some((rule, count))
+        })
+        .collect()
+}
+
+/// Periodically persists the in-memory standings
+pub async fn run(pool: PgPool, reputation: Reputation) {
+    loop {
+        tokio::time::sleep(FLUSH_INTERVAL).await;
+
+        if let Err(e) = reputation.flush(&pool).await {
+            error!("Author reputation flush failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(
+        counted_action: ModerationAction,
+        min_count: usize,
+        window_secs: i32,
+    ) -> ReputationRule {
+        ReputationRule {
+            id: 1,
+            counted_action,
+            min_count,
+            window_secs,
+            action: ModerationAction::Rejected,
+        }
+    }
+
+    fn verdict(at: DateTime<Utc>, status: ModerationAction) -> VerdictEntry {
+        VerdictEntry { at, status }
+    }
+
+    #[test]
+    fn tripped_counts_the_matching_action() {
+        let now = Utc::now();
+        let rules = [rule(ModerationAction::NeedsReview, 2, 60)];
+        let verdicts = [
+            verdict(now, ModerationAction::NeedsReview),
+            verdict(now, ModerationAction::Rejected),
+            verdict(now, ModerationAction::NeedsReview),
+        ];
+
+        let hits = tripped(&rules, &verdicts, now);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].1, 2);
+        assert!(tripped(&rules, &verdicts[..2], now).is_empty());
+    }
+
+    #[test]
+    fn tripped_window_boundary() {
+        let now = Utc::now();
+        let rules = [rule(ModerationAction::Rejected, 1, 60)];
+        let at = |secs| {
+            verdict(
+                now - chrono::Duration::seconds(secs),
+                ModerationAction::Rejected,
+            )
+        };
+
+        assert_eq!(tripped(&rules, &[at(59)], now).len(), 1);
+        // Exactly `window_secs` old is already outside
+        assert!(tripped(&rules, &[at(60)], now).is_empty());
+        assert!(tripped(&rules, &[at(61)], now).is_empty());
+    }
+
+    #[test]
+    fn prune_drops_verdicts_older_than_the_max_window() {
+        let now = Utc::now();
+        let max = chrono::Duration::from_std(MAX_WINDOW).unwrap();
+        let mut standing = Standing {
+            verdicts: [
+                verdict(
+                    now - max - chrono::Duration::seconds(1),
+                    ModerationAction::Rejected,
+                ),
+                verdict(now - max, ModerationAction::NeedsReview),
+                verdict(now, ModerationAction::Approved),
+            ]
+            .into(),
+            dirty: false,
+        };
+
+        standing.prune(now);
+        let left: Vec<_> = standing.verdicts.iter().map(|v| v.status).collect();
+        assert_eq!(
+            left,
+            [ModerationAction::NeedsReview, ModerationAction::Approved]
+        );
+    }
+
+    #[tokio::test]
+    async fn assess_records_the_verdict_without_its_escalation() {
+        let reputation = Reputation::new();
+        let rules = [rule(ModerationAction::NeedsReview, 2, 60)];
+
+        for _ in 0..2 {
+            let hits = reputation
+                .assess(&rules, "author", ModerationAction::NeedsReview)
+                .await;
+            assert!(hits.is_empty());
+        }
+        // The third comment is escalated to REJECTED by the two earlier ones, but is
+        // still counted as the NEEDS_REVIEW it was on its own
+        let hits = reputation
+            .assess(&rules, "author", ModerationAction::NeedsReview)
+            .await;
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].1, 2);
+
+        let statuses: Vec<_> = reputation
+            .verdicts("author")
+            .await
+            .iter()
+            .map(|v| v.status)
+            .collect();
+        assert_eq!(statuses, [ModerationAction::NeedsReview; 3]);
+        assert!(reputation.verdicts("someone_else").await.is_empty());
+    }
+}
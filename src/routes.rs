@@ -1,19 +1,31 @@
 use axum::{
-    extract::{Path, State},
-    response::Json,
-    routing::{delete, get, post},
+    extract::{Extension, Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Json, Response},
+    routing::{delete, get, patch, post},
     Router,
 };
 use garde::Validate;
 use regex::Regex;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
+use std::sync::Arc;
 
-use crate::{cache::ModerationCache, errors::Error, models::*};
+use crate::{
+    cache::ModerationCache,
+    detectors::Detectors,
+    errors::Error,
+    expressions,
+    history::{self, snapshot},
+    models::*,
+    moderation::moderate_comment,
+    reputation, urls,
+};
 
 #[derive(Clone)]
 pub struct AppContext {
     pub pool: PgPool,
     pub cache: ModerationCache,
+    pub detectors: Arc<Detectors>,
 }
 
 pub fn app_routes() -> Router<AppContext> {
@@ -22,23 +34,114 @@ pub fn app_routes() -> Router<AppContext> {
         .route("/moderate", post(api_moderate))
         // Bad words
         .route("/rules/badwords", get(list_badwords).post(add_badword))
+        .route(
+            "/rules/badwords/id/{id}",
+            patch(update_badword).delete(delete_badword_by_id),
+        )
+        // Deletes by the word itself
         .route("/rules/badwords/{word}", delete(delete_badword))
+        // Allowlisted words, exempt bad word hits inside them
+        .route(
+            "/rules/allowwords",
+            get(list_allowwords).post(add_allowword),
+        )
+        .route(
+            "/rules/allowwords/id/{id}",
+            patch(update_allowword).delete(delete_allowword_by_id),
+        )
+        // Deletes by the word itself
+        .route("/rules/allowwords/{word}", delete(delete_allowword))
         // Regex rules
         .route("/rules/regex", get(list_regex).post(add_regex))
-        .route("/rules/regex/{id}", delete(delete_regex))
+        .route(
+            "/rules/regex/{id}",
+            patch(update_regex).delete(delete_regex),
+        )
+        // Boolean combinations of rule hits and comment context
+        .route(
+            "/rules/expressions",
+            get(list_expressions).post(add_expression),
+        )
+        .route(
+            "/rules/expressions/{id}",
+            patch(update_expression).delete(delete_expression),
+        )
+        // Built-in personal data detectors, only their configuration can change. POST saves
+        // the caller's own settings for a detector, deleting them falls back to the default.
+        .route(
+            "/rules/pii",
+            get(list_pii_detectors).post(override_pii_detector),
+        )
+        .route(
+            "/rules/pii/{id}",
+            patch(update_pii_detector).delete(delete_pii_detector),
+        )
+        // Link domains, matched on the domain and all of its subdomains
+        .route("/rules/domains", get(list_domains).post(add_domain))
+        .route(
+            "/rules/domains/{id}",
+            patch(update_domain).delete(delete_domain),
+        )
         // Settings
         .route("/rules/settings", get(list_settings).post(insert_setting))
+        // Reason code categories, POST replaces the description of an existing one
+        .route(
+            "/rules/categories",
+            get(list_categories).post(insert_category),
+        )
+        .route("/rules/categories/{name}", delete(delete_category))
+        // Per-category hit thresholds, POST replaces the action of the same category and hit count
+        .route(
+            "/rules/thresholds",
+            get(list_thresholds).post(insert_threshold),
+        )
+        .route("/rules/thresholds/{id}", delete(delete_threshold))
+        // Repeat offender escalation, POST replaces the action of the same counted action,
+        // count and window
+        .route(
+            "/rules/reputation",
+            get(list_reputation_rules).post(insert_reputation_rule),
+        )
+        .route("/rules/reputation/{id}", delete(delete_reputation_rule))
+        // Standing of a single author, DELETE forgets their verdicts
+        .route(
+            "/authors/{author_id}",
+            get(get_author_standing).delete(reset_author_standing),
+        )
+        // Localized reason templates, POST replaces the template of the same locale and key
+        .route("/rules/reasons", get(list_reasons).post(upsert_reason))
+        .route("/rules/reasons/{id}", delete(delete_reason))
+        // Bulk import / export
+        .route("/rules/export", get(export_rules))
+        .route("/rules/import", post(import_rules))
+        // Shadow rule counters
+        .route("/rules/shadow", get(list_shadow_hits))
+        // Change history
+        .route("/rules/history", get(list_history))
+        .route("/rules/history/{id}/rollback", post(rollback_rule))
 }
 
 async fn api_moderate(
     State(state): State<AppContext>,
+    Extension(tenant): Extension<ApiKeyId>,
+    headers: HeaderMap,
     Json(payload): Json<CommentRequest>,
 ) -> Result<Json<ApiResponse<ModerationResponse>>, Error> {
     payload
         .validate()
         .map_err(|e| Error::Validation(e.to_string()))?;
 
-    let moderation_result = moderate_comment(&state.cache, &payload);
+    let accept_language = headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok());
+    let moderation_result = moderate_comment(
+        &state.cache,
+        &state.detectors,
+        &payload,
+        &tenant.0,
+        accept_language,
+    )
+    .await;
 
     Ok(Json(ApiResponse {
         success: true,
@@ -49,48 +152,67 @@ async fn api_moderate(
 
 async fn list_badwords(
     State(state): State<AppContext>,
-) -> Result<Json<ApiResponse<Vec<(String, String)>>>, Error> {
-    let items = state
-        .cache
-        .bad_words
-        .iter()
-        .map(|(k, value)| (k.to_string(), value.to_string()))
-        .collect();
+    Query(query): Query<RuleListQuery>,
+) -> Result<Json<ApiResponse<Page<BadWordRow>>>, Error> {
+    query
+        .validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM bad_words WHERE TRUE");
+    push_list_filters(&mut builder, &query, &["word"])?;
+    let limit = push_page(&mut builder, &query);
+
+    let rows: Vec<BadWordRow> = builder.build_query_as().fetch_all(&state.pool).await?;
 
     Ok(Json(ApiResponse {
         success: true,
         message: "Bad words retrieved successfully".to_string(),
-        data: items,
+        data: into_page(rows, limit, |r| r.id),
     }))
 }
 
 async fn add_badword(
     State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
     Json(body): Json<BadWordCreate>,
 ) -> Result<Json<ApiResponse<Option<String>>>, Error> {
     body.validate()
         .map_err(|e| Error::Validation(e.to_string()))?;
 
-    sqlx::query(
-        "INSERT INTO bad_words (word, moderation_action) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+    let mut tx = state.pool.begin().await?;
+
+    let inserted: Option<i32> = sqlx::query_scalar(
+        "INSERT INTO bad_words (word, moderation_action, valid_from, valid_until, enabled, mode, reason_code, category, weight, language)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) ON CONFLICT DO NOTHING RETURNING id",
     )
     .bind(&body.word)
-    .bind(&body.action)
-    .execute(&state.pool)
+    .bind(body.action)
+    .bind(body.valid_from)
+    .bind(body.valid_until)
+    .bind(body.enabled)
+    .bind(body.mode)
+    .bind(&body.reason_code)
+    .bind(&body.category)
+    .bind(body.weight)
+    .bind(&body.language)
+    .fetch_optional(&mut *tx)
     .await?;
 
-    let rows: Vec<BadWordRow> = sqlx::query_as("SELECT * FROM bad_words ORDER BY id")
-        .fetch_all(&state.pool)
+    if let Some(id) = inserted {
+        history::record_change(
+            &mut tx,
+            &actor,
+            RuleKind::BadWord,
+            &id.to_string(),
+            RuleOperation::Create,
+            None,
+        )
         .await?;
+    }
 
-    state
-        .cache
-        .load_bad_words(
-            rows.into_iter()
-                .map(|r| (r.word, r.moderation_action.to_string()))
-                .collect(),
-        )
-        .await;
+    tx.commit().await?;
+
+    state.cache.reload_bad_words(&state.pool).await?;
 
     Ok(Json(ApiResponse {
         success: true,
@@ -99,54 +221,345 @@ async fn add_badword(
     }))
 }
 
+/// Deletes the row of `?language=`, the one for every language when it's missing
 async fn delete_badword(
     State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
     Path(word): Path<String>,
+    Query(query): Query<BadWordLanguage>,
 ) -> Result<Json<ApiResponse<Option<String>>>, Error> {
-    let res = sqlx::query!("DELETE FROM bad_words WHERE word = $1", word)
-        .execute(&state.pool)
-        .await?;
+    query
+        .validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let id: Option<i32> = sqlx::query_scalar(
+        "SELECT id FROM bad_words WHERE word = $1 AND language IS NOT DISTINCT FROM $2",
+    )
+    .bind(&word)
+    .bind(&query.language)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    let Some(id) = id else {
+        return Err(Error::NotFound);
+    };
+
+    delete_badword_by_id(State(state), Extension(actor), Path(id)).await
+}
+
+async fn delete_badword_by_id(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(id): Path<i32>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    let mut tx = state.pool.begin().await?;
+
+    let before: Option<serde_json::Value> =
+        sqlx::query_scalar("DELETE FROM bad_words WHERE id = $1 RETURNING to_jsonb(bad_words)")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+    let Some(before) = before else {
+        return Err(Error::NotFound);
+    };
+    expressions::check_references(&mut tx).await?;
+
+    history::record(
+        &mut tx,
+        &actor,
+        RuleKind::BadWord,
+        &id.to_string(),
+        RuleOperation::Delete,
+        Some(before),
+        None,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_bad_words(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Bad word deleted successfully".to_string(),
+        data: None,
+    }))
+}
+
+async fn update_badword(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    Json(body): Json<BadWordUpdate>,
+) -> Result<Response, Error> {
+    body.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let expected = expected_version(&headers, body.version)?;
+
+    let mut tx = state.pool.begin().await?;
 
-    if res.rows_affected() == 0 {
+    let key = id.to_string();
+    let Some(before) = snapshot(&mut tx, RuleKind::BadWord, &key).await? else {
         return Err(Error::NotFound);
+    };
+
+    let updated: Option<BadWordRow> = sqlx::query_as(
+        "UPDATE bad_words
+         SET word = COALESCE($2, word),
+             moderation_action = COALESCE($3, moderation_action),
+             valid_from = CASE WHEN $5 THEN $6 ELSE valid_from END,
+             valid_until = CASE WHEN $7 THEN $8 ELSE valid_until END,
+             enabled = COALESCE($9, enabled),
+             mode = COALESCE($10, mode),
+             reason_code = COALESCE($11, reason_code),
+             category = COALESCE($12, category),
+             weight = COALESCE($13, weight),
+             language = CASE WHEN $14 THEN $15 ELSE language END,
+             version = version + 1
+         WHERE id = $1 AND version = $4
+         RETURNING *",
+    )
+    .bind(id)
+    .bind(&body.word)
+    .bind(body.action)
+    .bind(expected)
+    .bind(body.valid_from.is_some())
+    .bind(body.valid_from.flatten())
+    .bind(body.valid_until.is_some())
+    .bind(body.valid_until.flatten())
+    .bind(body.enabled)
+    .bind(body.mode)
+    .bind(&body.reason_code)
+    .bind(&body.category)
+    .bind(body.weight)
+    .bind(body.language.is_some())
+    .bind(body.language.clone().flatten())
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| unique_violation_as(e, "bad word already exists for this language"))?;
+
+    let Some(row) = updated else {
+        return Err(Error::Conflict);
+    };
+
+    history::record_change(
+        &mut tx,
+        &actor,
+        RuleKind::BadWord,
+        &key,
+        RuleOperation::Update,
+        Some(before),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_bad_words(&state.pool).await?;
+
+    Ok(with_etag(
+        row.version,
+        ApiResponse {
+            success: true,
+            message: "Bad word updated successfully".to_string(),
+            data: row,
+        },
+    ))
+}
+
+async fn list_allowwords(
+    State(state): State<AppContext>,
+    Query(query): Query<RuleListQuery>,
+) -> Result<Json<ApiResponse<Page<AllowWordRow>>>, Error> {
+    query
+        .validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    if query.action.is_some() || query.category.is_some() {
+        return Err(Error::Validation(
+            "allow words can't be filtered by action or category".into(),
+        ));
     }
 
-    let rows: Vec<BadWordRow> = sqlx::query_as("SELECT * FROM bad_words ORDER BY id")
-        .fetch_all(&state.pool)
+    let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM allow_words WHERE TRUE");
+    push_list_filters(&mut builder, &query, &["word"])?;
+    let limit = push_page(&mut builder, &query);
+
+    let rows: Vec<AllowWordRow> = builder.build_query_as().fetch_all(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Allow words retrieved successfully".to_string(),
+        data: into_page(rows, limit, |r| r.id),
+    }))
+}
+
+async fn add_allowword(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Json(body): Json<AllowWordCreate>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    body.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let mut tx = state.pool.begin().await?;
+
+    insert_allow_word(&mut tx, &actor, &body).await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_allow_words(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Allow word added successfully".to_string(),
+        data: None,
+    }))
+}
+
+async fn delete_allowword(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(word): Path<String>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    let id: Option<i32> = sqlx::query_scalar("SELECT id FROM allow_words WHERE word = $1")
+        .bind(&word)
+        .fetch_optional(&state.pool)
         .await?;
 
-    state
-        .cache
-        .load_bad_words(
-            rows.into_iter()
-                .map(|r| (r.word, r.moderation_action.to_string()))
-                .collect(),
-        )
-        .await;
+    let Some(id) = id else {
+        return Err(Error::NotFound);
+    };
+
+    delete_allowword_by_id(State(state), Extension(actor), Path(id)).await
+}
+
+async fn delete_allowword_by_id(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(id): Path<i32>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    let mut tx = state.pool.begin().await?;
+
+    let before: Option<serde_json::Value> =
+        sqlx::query_scalar("DELETE FROM allow_words WHERE id = $1 RETURNING to_jsonb(allow_words)")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+    let Some(before) = before else {
+        return Err(Error::NotFound);
+    };
+
+    history::record(
+        &mut tx,
+        &actor,
+        RuleKind::AllowWord,
+        &id.to_string(),
+        RuleOperation::Delete,
+        Some(before),
+        None,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_allow_words(&state.pool).await?;
 
     Ok(Json(ApiResponse {
         success: true,
-        message: "Bad word deleted successfully".to_string(),
+        message: "Allow word deleted successfully".to_string(),
         data: None,
     }))
 }
 
+async fn update_allowword(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    Json(body): Json<AllowWordUpdate>,
+) -> Result<Response, Error> {
+    body.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let expected = expected_version(&headers, body.version)?;
+
+    let mut tx = state.pool.begin().await?;
+
+    let key = id.to_string();
+    let Some(before) = snapshot(&mut tx, RuleKind::AllowWord, &key).await? else {
+        return Err(Error::NotFound);
+    };
+
+    let updated: Option<AllowWordRow> = sqlx::query_as(
+        "UPDATE allow_words
+         SET word = COALESCE($2, word),
+             version = version + 1
+         WHERE id = $1 AND version = $3
+         RETURNING *",
+    )
+    .bind(id)
+    .bind(&body.word)
+    .bind(expected)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| unique_violation_as(e, "allow word already exists"))?;
+
+    let Some(row) = updated else {
+        return Err(Error::Conflict);
+    };
+
+    history::record_change(
+        &mut tx,
+        &actor,
+        RuleKind::AllowWord,
+        &key,
+        RuleOperation::Update,
+        Some(before),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_allow_words(&state.pool).await?;
+
+    Ok(with_etag(
+        row.version,
+        ApiResponse {
+            success: true,
+            message: "Allow word updated successfully".to_string(),
+            data: row,
+        },
+    ))
+}
+
 async fn list_regex(
     State(state): State<AppContext>,
-) -> Result<Json<ApiResponse<Vec<RegexRuleRow>>>, Error> {
-    let rows: Vec<RegexRuleRow> = sqlx::query_as("SELECT * FROM regex_rules ORDER BY id")
-        .fetch_all(&state.pool)
-        .await?;
+    Query(query): Query<RuleListQuery>,
+) -> Result<Json<ApiResponse<Page<RegexRuleRow>>>, Error> {
+    query
+        .validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM regex_rules WHERE TRUE");
+    push_list_filters(&mut builder, &query, &["pattern", "description"])?;
+    let limit = push_page(&mut builder, &query);
+
+    let rows: Vec<RegexRuleRow> = builder.build_query_as().fetch_all(&state.pool).await?;
 
     Ok(Json(ApiResponse {
         success: true,
         message: "Regex rules retrieved successfully".to_string(),
-        data: rows,
+        data: into_page(rows, limit, |r| r.id),
     }))
 }
 
 async fn add_regex(
     State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
     Json(body): Json<RegexRuleCreate>,
 ) -> Result<Json<ApiResponse<Option<String>>>, Error> {
     body.validate()
@@ -154,33 +567,39 @@ async fn add_regex(
 
     let _ = Regex::new(&body.pattern).map_err(|e| Error::Regex(e.to_string()))?;
 
-    let _: RegexRuleRow = sqlx::query_as(
-        "INSERT INTO regex_rules (pattern, description, moderation_action) VALUES ($1, $2, $3) RETURNING id, pattern, description, moderation_action"
+    let mut tx = state.pool.begin().await?;
+
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO regex_rules (pattern, description, moderation_action, valid_from, valid_until, enabled, mode, reason_code, category, weight, language)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) RETURNING id",
     )
-        .bind(&body.pattern)
-        .bind(&body.description)
-        .bind(&body.action)
-        .fetch_one(&state.pool)
-        .await?;
+    .bind(&body.pattern)
+    .bind(&body.description)
+    .bind(body.action)
+    .bind(body.valid_from)
+    .bind(body.valid_until)
+    .bind(body.enabled)
+    .bind(body.mode)
+    .bind(&body.reason_code)
+    .bind(&body.category)
+    .bind(body.weight)
+    .bind(&body.language)
+    .fetch_one(&mut *tx)
+    .await?;
 
-    let rows: Vec<RegexRuleRow> = sqlx::query_as("SELECT * FROM regex_rules ORDER BY id")
-        .fetch_all(&state.pool)
-        .await?;
+    history::record_change(
+        &mut tx,
+        &actor,
+        RuleKind::Regex,
+        &id.to_string(),
+        RuleOperation::Create,
+        None,
+    )
+    .await?;
 
-    let compiled_all = rows
-        .into_iter()
-        .map(|r| {
-            let re = Regex::new(&r.pattern).unwrap();
-            (
-                r.id,
-                re,
-                r.description.unwrap_or_else(|| "Regex kuralı".into()),
-                r.moderation_action.to_string(),
-            )
-        })
-        .collect();
+    tx.commit().await?;
 
-    state.cache.load_regex_rules(compiled_all).await;
+    state.cache.reload_regex_rules(&state.pool).await?;
 
     Ok(Json(ApiResponse {
         success: true,
@@ -189,36 +608,126 @@ async fn add_regex(
     }))
 }
 
+async fn update_regex(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    Json(body): Json<RegexRuleUpdate>,
+) -> Result<Response, Error> {
+    body.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    if let Some(pattern) = &body.pattern {
+        Regex::new(pattern).map_err(|e| Error::Regex(e.to_string()))?;
+    }
+
+    let expected = expected_version(&headers, body.version)?;
+
+    let mut tx = state.pool.begin().await?;
+
+    let key = id.to_string();
+    let Some(before) = snapshot(&mut tx, RuleKind::Regex, &key).await? else {
+        return Err(Error::NotFound);
+    };
+
+    let updated: Option<RegexRuleRow> = sqlx::query_as(
+        "UPDATE regex_rules
+         SET pattern = COALESCE($2, pattern),
+             description = CASE WHEN $17 THEN $3 ELSE description END,
+             moderation_action = COALESCE($4, moderation_action),
+             valid_from = CASE WHEN $6 THEN $7 ELSE valid_from END,
+             valid_until = CASE WHEN $8 THEN $9 ELSE valid_until END,
+             enabled = COALESCE($10, enabled),
+             mode = COALESCE($11, mode),
+             reason_code = COALESCE($12, reason_code),
+             category = COALESCE($13, category),
+             weight = COALESCE($14, weight),
+             language = CASE WHEN $15 THEN $16 ELSE language END,
+             version = version + 1
+         WHERE id = $1 AND version = $5
+         RETURNING *",
+    )
+    .bind(id)
+    .bind(&body.pattern)
+    .bind(body.description.clone().flatten())
+    .bind(body.action)
+    .bind(expected)
+    .bind(body.valid_from.is_some())
+    .bind(body.valid_from.flatten())
+    .bind(body.valid_until.is_some())
+    .bind(body.valid_until.flatten())
+    .bind(body.enabled)
+    .bind(body.mode)
+    .bind(&body.reason_code)
+    .bind(&body.category)
+    .bind(body.weight)
+    .bind(body.language.is_some())
+    .bind(body.language.clone().flatten())
+    .bind(body.description.is_some())
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(row) = updated else {
+        return Err(Error::Conflict);
+    };
+
+    history::record_change(
+        &mut tx,
+        &actor,
+        RuleKind::Regex,
+        &key,
+        RuleOperation::Update,
+        Some(before),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_regex_rules(&state.pool).await?;
+
+    Ok(with_etag(
+        row.version,
+        ApiResponse {
+            success: true,
+            message: "Regex rule updated successfully".to_string(),
+            data: row,
+        },
+    ))
+}
+
 async fn delete_regex(
     State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
     Path(id): Path<i32>,
 ) -> Result<Json<ApiResponse<Option<String>>>, Error> {
-    let res = sqlx::query!("DELETE FROM regex_rules WHERE id = $1", id)
-        .execute(&state.pool)
-        .await?;
+    let mut tx = state.pool.begin().await?;
 
-    if res.rows_affected() == 0 {
+    let before: Option<serde_json::Value> =
+        sqlx::query_scalar("DELETE FROM regex_rules WHERE id = $1 RETURNING to_jsonb(regex_rules)")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+    if before.is_none() {
         return Err(Error::NotFound);
     }
+    expressions::check_references(&mut tx).await?;
 
-    let rows: Vec<RegexRuleRow> = sqlx::query_as("SELECT * FROM regex_rules ORDER BY id")
-        .fetch_all(&state.pool)
-        .await?;
+    history::record(
+        &mut tx,
+        &actor,
+        RuleKind::Regex,
+        &id.to_string(),
+        RuleOperation::Delete,
+        before,
+        None,
+    )
+    .await?;
 
-    let compiled_all = rows
-        .into_iter()
-        .map(|r| {
-            let re = regex::Regex::new(&r.pattern).unwrap();
-            (
-                r.id,
-                re,
-                r.description.unwrap_or_else(|| "Regex kuralı".into()),
-                r.moderation_action.to_string(),
-            )
-        })
-        .collect();
+    tx.commit().await?;
 
-    state.cache.load_regex_rules(compiled_all).await;
+    state.cache.reload_regex_rules(&state.pool).await?;
 
     Ok(Json(ApiResponse {
         success: true,
@@ -227,84 +736,2063 @@ async fn delete_regex(
     }))
 }
 
-async fn list_settings(
+async fn list_expressions(
     State(state): State<AppContext>,
-) -> Result<Json<ApiResponse<Vec<SettingRow>>>, Error> {
-    let rows: Vec<SettingRow> = sqlx::query_as("SELECT * FROM settings ORDER BY key")
-        .fetch_all(&state.pool)
-        .await?;
+    Query(query): Query<RuleListQuery>,
+) -> Result<Json<ApiResponse<Page<RuleExpressionRow>>>, Error> {
+    query
+        .validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM rule_expressions WHERE TRUE");
+    push_list_filters(&mut builder, &query, &["description"])?;
+    let limit = push_page(&mut builder, &query);
+
+    let rows: Vec<RuleExpressionRow> = builder.build_query_as().fetch_all(&state.pool).await?;
 
     Ok(Json(ApiResponse {
         success: true,
-        message: "Settings retrieved successfully".to_string(),
-        data: rows,
+        message: "Rule expressions retrieved successfully".to_string(),
+        data: into_page(rows, limit, |r| r.id),
     }))
 }
 
-async fn insert_setting(
+async fn add_expression(
     State(state): State<AppContext>,
-    Json(body): Json<SettingInsert>,
+    Extension(actor): Extension<ApiKeyId>,
+    Json(body): Json<RuleExpressionCreate>,
 ) -> Result<Json<ApiResponse<Option<String>>>, Error> {
     body.validate()
         .map_err(|e| Error::Validation(e.to_string()))?;
 
-    sqlx::query!(
-        "INSERT INTO settings (key, value) VALUES ($1, $2)
-         ON CONFLICT (key) DO UPDATE SET value = EXCLUDED.value",
-        body.key,
-        body.value
+    let mut tx = state.pool.begin().await?;
+
+    body.expression.validate(&mut tx).await?;
+
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO rule_expressions (expression, description, moderation_action, reason_code, category, weight, enabled, mode)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
     )
-    .execute(&state.pool)
+    .bind(sqlx::types::Json(&body.expression))
+    .bind(&body.description)
+    .bind(body.action)
+    .bind(&body.reason_code)
+    .bind(&body.category)
+    .bind(body.weight)
+    .bind(body.enabled)
+    .bind(body.mode)
+    .fetch_one(&mut *tx)
     .await?;
 
-    let rows: Vec<SettingRow> = sqlx::query_as("SELECT * FROM settings ORDER BY key")
-        .fetch_all(&state.pool)
-        .await?;
+    history::record_change(
+        &mut tx,
+        &actor,
+        RuleKind::Expression,
+        &id.to_string(),
+        RuleOperation::Create,
+        None,
+    )
+    .await?;
 
-    state
-        .cache
-        .load_settings(rows.into_iter().map(|r| (r.key, r.value)).collect())
-        .await;
+    tx.commit().await?;
+
+    state.cache.reload_rule_expressions(&state.pool).await?;
 
     Ok(Json(ApiResponse {
         success: true,
-        message: "Setting updated successfully".to_string(),
+        message: "Rule expression added successfully".to_string(),
         data: None,
     }))
 }
 
-// Check comment here
-pub fn moderate_comment(cache: &ModerationCache, req: &CommentRequest) -> ModerationResponse {
-    let text = req.content.to_lowercase();
+async fn update_expression(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    Json(body): Json<RuleExpressionUpdate>,
+) -> Result<Response, Error> {
+    body.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let expected = expected_version(&headers, body.version)?;
 
-    if let Some(bundle) = cache.bad_words_matcher.read().unwrap().as_ref() {
-        if let Some(mat) = bundle.ac.find(&text) {
-            let pat_index = mat.pattern();
-            let word = &bundle.words[pat_index];
-            let action = &bundle.actions[pat_index];
+    let mut tx = state.pool.begin().await?;
 
-            return ModerationResponse {
-                status: action.clone(),
-                reason: Some(format!("Küfür tespit edildi: {word}")),
-            };
-        }
+    if let Some(expression) = &body.expression {
+        expression.validate(&mut tx).await?;
     }
 
-    if let Some(bundle) = cache.regex_set_bundle.read().unwrap().as_ref() {
-        let matches = bundle.set.matches(&text);
-        if let Some(idx) = matches.into_iter().next() {
-            let action = &bundle.actions[idx];
-            let desc = &bundle.descriptions[idx];
+    let key = id.to_string();
+    let Some(before) = snapshot(&mut tx, RuleKind::Expression, &key).await? else {
+        return Err(Error::NotFound);
+    };
+
+    let updated: Option<RuleExpressionRow> = sqlx::query_as(
+        "UPDATE rule_expressions
+         SET expression = COALESCE($2, expression),
+             description = CASE WHEN $11 THEN $3 ELSE description END,
+             moderation_action = COALESCE($4, moderation_action),
+             reason_code = COALESCE($6, reason_code),
+             category = COALESCE($7, category),
+             weight = COALESCE($8, weight),
+             enabled = COALESCE($9, enabled),
+             mode = COALESCE($10, mode),
+             version = version + 1
+         WHERE id = $1 AND version = $5
+         RETURNING *",
+    )
+    .bind(id)
+    .bind(body.expression.as_ref().map(sqlx::types::Json))
+    .bind(body.description.clone().flatten())
+    .bind(body.action)
+    .bind(expected)
+    .bind(&body.reason_code)
+    .bind(&body.category)
+    .bind(body.weight)
+    .bind(body.enabled)
+    .bind(body.mode)
+    .bind(body.description.is_some())
+    .fetch_optional(&mut *tx)
+    .await?;
 
-            return ModerationResponse {
-                status: action.clone(),
-                reason: Some(desc.clone()),
-            };
-        }
-    }
+    let Some(row) = updated else {
+        return Err(Error::Conflict);
+    };
+
+    history::record_change(
+        &mut tx,
+        &actor,
+        RuleKind::Expression,
+        &key,
+        RuleOperation::Update,
+        Some(before),
+    )
+    .await?;
 
-    ModerationResponse {
-        status: "APPROVED".into(),
-        reason: None,
+    tx.commit().await?;
+
+    state.cache.reload_rule_expressions(&state.pool).await?;
+
+    Ok(with_etag(
+        row.version,
+        ApiResponse {
+            success: true,
+            message: "Rule expression updated successfully".to_string(),
+            data: row,
+        },
+    ))
+}
+
+async fn delete_expression(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(id): Path<i32>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    let mut tx = state.pool.begin().await?;
+
+    let before: Option<serde_json::Value> = sqlx::query_scalar(
+        "DELETE FROM rule_expressions WHERE id = $1 RETURNING to_jsonb(rule_expressions)",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if before.is_none() {
+        return Err(Error::NotFound);
     }
+
+    history::record(
+        &mut tx,
+        &actor,
+        RuleKind::Expression,
+        &id.to_string(),
+        RuleOperation::Delete,
+        before,
+        None,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_rule_expressions(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Rule expression deleted successfully".to_string(),
+        data: None,
+    }))
+}
+
+/// The defaults and the caller's own rows
+async fn list_pii_detectors(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+) -> Result<Json<ApiResponse<Vec<PiiDetectorRow>>>, Error> {
+    let rows: Vec<PiiDetectorRow> = sqlx::query_as(
+        "SELECT * FROM pii_detectors
+         WHERE tenant IS NULL OR tenant = $1
+         ORDER BY detector, tenant NULLS FIRST",
+    )
+    .bind(&actor.0)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "PII detectors retrieved successfully".to_string(),
+        data: rows,
+    }))
+}
+
+async fn update_pii_detector(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    Json(body): Json<PiiDetectorUpdate>,
+) -> Result<Response, Error> {
+    body.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let expected = expected_version(&headers, body.version)?;
+
+    let mut tx = state.pool.begin().await?;
+
+    // Other keys' rows are out of reach, the defaults are shared
+    let visible: bool = sqlx::query_scalar(
+        "SELECT EXISTS (
+             SELECT 1 FROM pii_detectors WHERE id = $1 AND (tenant IS NULL OR tenant = $2)
+         )",
+    )
+    .bind(id)
+    .bind(&actor.0)
+    .fetch_one(&mut *tx)
+    .await?;
+    if !visible {
+        return Err(Error::NotFound);
+    }
+
+    let key = id.to_string();
+    let Some(before) = snapshot(&mut tx, RuleKind::PiiDetector, &key).await? else {
+        return Err(Error::NotFound);
+    };
+
+    let updated: Option<PiiDetectorRow> = sqlx::query_as(
+        "UPDATE pii_detectors
+         SET moderation_action = COALESCE($2, moderation_action),
+             weight = COALESCE($4, weight),
+             enabled = COALESCE($5, enabled),
+             mode = COALESCE($6, mode),
+             version = version + 1
+         WHERE id = $1 AND version = $3
+         RETURNING *",
+    )
+    .bind(id)
+    .bind(body.action)
+    .bind(expected)
+    .bind(body.weight)
+    .bind(body.enabled)
+    .bind(body.mode)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(row) = updated else {
+        return Err(Error::Conflict);
+    };
+
+    history::record_change(
+        &mut tx,
+        &actor,
+        RuleKind::PiiDetector,
+        &key,
+        RuleOperation::Update,
+        Some(before),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_pii_detectors(&state.pool).await?;
+
+    Ok(with_etag(
+        row.version,
+        ApiResponse {
+            success: true,
+            message: "PII detector updated successfully".to_string(),
+            data: row,
+        },
+    ))
+}
+
+async fn override_pii_detector(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Json(body): Json<PiiDetectorOverride>,
+) -> Result<Json<ApiResponse<PiiDetectorRow>>, Error> {
+    body.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let mut tx = state.pool.begin().await?;
+
+    let existing: Option<i32> =
+        sqlx::query_scalar("SELECT id FROM pii_detectors WHERE tenant = $1 AND detector = $2")
+            .bind(&actor.0)
+            .bind(body.detector)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+    let before = match existing {
+        Some(id) => snapshot(&mut tx, RuleKind::PiiDetector, &id.to_string()).await?,
+        None => None,
+    };
+
+    let row: PiiDetectorRow = sqlx::query_as(
+        "INSERT INTO pii_detectors (tenant, detector, moderation_action, weight, enabled, mode)
+         SELECT $1, detector, COALESCE($3, moderation_action), COALESCE($4, weight),
+                COALESCE($5, enabled), COALESCE($6, mode)
+         FROM pii_detectors WHERE tenant IS NULL AND detector = $2
+         ON CONFLICT (tenant, detector) DO UPDATE
+         SET moderation_action = COALESCE($3, pii_detectors.moderation_action),
+             weight = COALESCE($4, pii_detectors.weight),
+             enabled = COALESCE($5, pii_detectors.enabled),
+             mode = COALESCE($6, pii_detectors.mode),
+             version = pii_detectors.version + 1
+         RETURNING *",
+    )
+    .bind(&actor.0)
+    .bind(body.detector)
+    .bind(body.action)
+    .bind(body.weight)
+    .bind(body.enabled)
+    .bind(body.mode)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let operation = match before {
+        Some(_) => RuleOperation::Update,
+        None => RuleOperation::Create,
+    };
+    history::record_change(
+        &mut tx,
+        &actor,
+        RuleKind::PiiDetector,
+        &row.id.to_string(),
+        operation,
+        before,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_pii_detectors(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "PII detector saved successfully".to_string(),
+        data: row,
+    }))
+}
+
+/// Only the caller's own rows can be deleted, the defaults always stay
+async fn delete_pii_detector(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(id): Path<i32>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    let mut tx = state.pool.begin().await?;
+
+    let before: Option<serde_json::Value> = sqlx::query_scalar(
+        "DELETE FROM pii_detectors WHERE id = $1 AND tenant = $2
+         RETURNING to_jsonb(pii_detectors)",
+    )
+    .bind(id)
+    .bind(&actor.0)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(before) = before else {
+        return Err(Error::NotFound);
+    };
+
+    history::record(
+        &mut tx,
+        &actor,
+        RuleKind::PiiDetector,
+        &id.to_string(),
+        RuleOperation::Delete,
+        Some(before),
+        None,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_pii_detectors(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "PII detector deleted successfully".to_string(),
+        data: None,
+    }))
+}
+
+async fn list_domains(
+    State(state): State<AppContext>,
+    Query(query): Query<RuleListQuery>,
+) -> Result<Json<ApiResponse<Page<DomainRuleRow>>>, Error> {
+    query
+        .validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM domain_rules WHERE TRUE");
+    push_list_filters(&mut builder, &query, &["domain"])?;
+    let limit = push_page(&mut builder, &query);
+
+    let rows: Vec<DomainRuleRow> = builder.build_query_as().fetch_all(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Domain rules retrieved successfully".to_string(),
+        data: into_page(rows, limit, |r| r.id),
+    }))
+}
+
+async fn add_domain(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Json(body): Json<DomainRuleCreate>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    body.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let domain = canonical_domain(&body.domain)?;
+    let action = match (body.list, body.action) {
+        (DomainList::Deny, None) => {
+            return Err(Error::Validation("DENY entries need an action".into()))
+        }
+        (DomainList::Allow, Some(_)) => {
+            return Err(Error::Validation(
+                "ALLOW entries can't have an action".into(),
+            ))
+        }
+        (_, action) => action,
+    };
+
+    let mut tx = state.pool.begin().await?;
+
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO domain_rules (domain, list, moderation_action, reason_code, category, weight, enabled, mode)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
+    )
+    .bind(&domain)
+    .bind(body.list)
+    .bind(action)
+    .bind(&body.reason_code)
+    .bind(&body.category)
+    .bind(body.weight)
+    .bind(body.enabled)
+    .bind(body.mode)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| unique_violation_as(e, "domain already has a rule"))?;
+
+    history::record_change(
+        &mut tx,
+        &actor,
+        RuleKind::DomainRule,
+        &id.to_string(),
+        RuleOperation::Create,
+        None,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_domain_rules(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Domain rule added successfully".to_string(),
+        data: None,
+    }))
+}
+
+async fn update_domain(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    Json(body): Json<DomainRuleUpdate>,
+) -> Result<Response, Error> {
+    body.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let expected = expected_version(&headers, body.version)?;
+    let domain = body.domain.as_deref().map(canonical_domain).transpose()?;
+    if body.list == Some(DomainList::Allow) && body.action.is_some() {
+        return Err(Error::Validation(
+            "ALLOW entries can't have an action".into(),
+        ));
+    }
+
+    let mut tx = state.pool.begin().await?;
+
+    let key = id.to_string();
+    let Some(before) = snapshot(&mut tx, RuleKind::DomainRule, &key).await? else {
+        return Err(Error::NotFound);
+    };
+
+    // A DENY entry without an action, e.g. ALLOW moved to DENY, fails the table check
+    let updated: Option<DomainRuleRow> = sqlx::query_as(
+        "UPDATE domain_rules
+         SET domain = COALESCE($2, domain),
+             list = COALESCE($3, list),
+             moderation_action = CASE
+                 WHEN COALESCE($3, list) = 'ALLOW' THEN NULL
+                 ELSE COALESCE($4, moderation_action)
+             END,
+             reason_code = COALESCE($6, reason_code),
+             category = COALESCE($7, category),
+             weight = COALESCE($8, weight),
+             enabled = COALESCE($9, enabled),
+             mode = COALESCE($10, mode),
+             version = version + 1
+         WHERE id = $1 AND version = $5
+         RETURNING *",
+    )
+    .bind(id)
+    .bind(domain)
+    .bind(body.list)
+    .bind(body.action)
+    .bind(expected)
+    .bind(&body.reason_code)
+    .bind(&body.category)
+    .bind(body.weight)
+    .bind(body.enabled)
+    .bind(body.mode)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| unique_violation_as(e, "domain already has a rule"))?;
+
+    let Some(row) = updated else {
+        return Err(Error::Conflict);
+    };
+
+    history::record_change(
+        &mut tx,
+        &actor,
+        RuleKind::DomainRule,
+        &key,
+        RuleOperation::Update,
+        Some(before),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_domain_rules(&state.pool).await?;
+
+    Ok(with_etag(
+        row.version,
+        ApiResponse {
+            success: true,
+            message: "Domain rule updated successfully".to_string(),
+            data: row,
+        },
+    ))
+}
+
+async fn delete_domain(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(id): Path<i32>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    let mut tx = state.pool.begin().await?;
+
+    let before: Option<serde_json::Value> = sqlx::query_scalar(
+        "DELETE FROM domain_rules WHERE id = $1 RETURNING to_jsonb(domain_rules)",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if before.is_none() {
+        return Err(Error::NotFound);
+    }
+    expressions::check_references(&mut tx).await?;
+
+    history::record(
+        &mut tx,
+        &actor,
+        RuleKind::DomainRule,
+        &id.to_string(),
+        RuleOperation::Delete,
+        before,
+        None,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_domain_rules(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Domain rule deleted successfully".to_string(),
+        data: None,
+    }))
+}
+
+async fn list_settings(
+    State(state): State<AppContext>,
+) -> Result<Json<ApiResponse<Vec<SettingRow>>>, Error> {
+    let rows: Vec<SettingRow> = sqlx::query_as("SELECT * FROM settings ORDER BY key")
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Settings retrieved successfully".to_string(),
+        data: rows,
+    }))
+}
+
+async fn insert_setting(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Json(body): Json<SettingInsert>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    body.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let mut tx = state.pool.begin().await?;
+
+    upsert_setting(&mut tx, &actor, &body).await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_settings(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Setting updated successfully".to_string(),
+        data: None,
+    }))
+}
+
+async fn list_categories(
+    State(state): State<AppContext>,
+) -> Result<Json<ApiResponse<Vec<CategoryRow>>>, Error> {
+    let rows: Vec<CategoryRow> = sqlx::query_as("SELECT * FROM categories ORDER BY name")
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Categories retrieved successfully".to_string(),
+        data: rows,
+    }))
+}
+
+async fn insert_category(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Json(body): Json<CategoryInsert>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    body.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let mut tx = state.pool.begin().await?;
+
+    upsert_category(&mut tx, &actor, &body).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Category saved successfully".to_string(),
+        data: None,
+    }))
+}
+
+/// Fails while rules or thresholds still reference the category
+async fn delete_category(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(name): Path<String>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    let mut tx = state.pool.begin().await?;
+
+    let before: Option<serde_json::Value> =
+        sqlx::query_scalar("DELETE FROM categories WHERE name = $1 RETURNING to_jsonb(categories)")
+            .bind(&name)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+    let Some(before) = before else {
+        return Err(Error::NotFound);
+    };
+
+    history::record(
+        &mut tx,
+        &actor,
+        RuleKind::Category,
+        &name,
+        RuleOperation::Delete,
+        Some(before),
+        None,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Category deleted successfully".to_string(),
+        data: None,
+    }))
+}
+
+async fn list_thresholds(
+    State(state): State<AppContext>,
+) -> Result<Json<ApiResponse<Vec<CategoryThresholdRow>>>, Error> {
+    let rows: Vec<CategoryThresholdRow> =
+        sqlx::query_as("SELECT * FROM category_thresholds ORDER BY category, min_hits")
+            .fetch_all(&state.pool)
+            .await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Category thresholds retrieved successfully".to_string(),
+        data: rows,
+    }))
+}
+
+async fn insert_threshold(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Json(body): Json<CategoryThresholdInsert>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    body.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let mut tx = state.pool.begin().await?;
+
+    upsert_threshold(&mut tx, &actor, &body).await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_category_thresholds(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Category threshold saved successfully".to_string(),
+        data: None,
+    }))
+}
+
+async fn delete_threshold(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(id): Path<i32>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    let mut tx = state.pool.begin().await?;
+
+    let before: Option<serde_json::Value> = sqlx::query_scalar(
+        "DELETE FROM category_thresholds WHERE id = $1 RETURNING to_jsonb(category_thresholds)",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(before) = before else {
+        return Err(Error::NotFound);
+    };
+
+    history::record(
+        &mut tx,
+        &actor,
+        RuleKind::CategoryThreshold,
+        &id.to_string(),
+        RuleOperation::Delete,
+        Some(before),
+        None,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_category_thresholds(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Category threshold deleted successfully".to_string(),
+        data: None,
+    }))
+}
+
+async fn list_reputation_rules(
+    State(state): State<AppContext>,
+) -> Result<Json<ApiResponse<Vec<ReputationRuleRow>>>, Error> {
+    let rows: Vec<ReputationRuleRow> = sqlx::query_as(
+        "SELECT * FROM reputation_rules ORDER BY counted_action, window_secs, min_count",
+    )
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Reputation rules retrieved successfully".to_string(),
+        data: rows,
+    }))
+}
+
+async fn insert_reputation_rule(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Json(body): Json<ReputationRuleInsert>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    body.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let mut tx = state.pool.begin().await?;
+
+    let existing: Option<i32> = sqlx::query_scalar(
+        "SELECT id FROM reputation_rules
+         WHERE counted_action = $1 AND min_count = $2 AND window_secs = $3",
+    )
+    .bind(body.counted_action)
+    .bind(body.min_count)
+    .bind(body.window_secs)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let before = match existing {
+        Some(id) => snapshot(&mut tx, RuleKind::ReputationRule, &id.to_string()).await?,
+        None => None,
+    };
+
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO reputation_rules (counted_action, min_count, window_secs, moderation_action)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (counted_action, min_count, window_secs) DO UPDATE
+         SET moderation_action = EXCLUDED.moderation_action,
+             version = reputation_rules.version + 1
+         RETURNING id",
+    )
+    .bind(body.counted_action)
+    .bind(body.min_count)
+    .bind(body.window_secs)
+    .bind(body.action)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let operation = match before {
+        Some(_) => RuleOperation::Update,
+        None => RuleOperation::Create,
+    };
+    history::record_change(
+        &mut tx,
+        &actor,
+        RuleKind::ReputationRule,
+        &id.to_string(),
+        operation,
+        before,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_reputation_rules(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Reputation rule saved successfully".to_string(),
+        data: None,
+    }))
+}
+
+async fn delete_reputation_rule(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(id): Path<i32>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    let mut tx = state.pool.begin().await?;
+
+    let before: Option<serde_json::Value> = sqlx::query_scalar(
+        "DELETE FROM reputation_rules WHERE id = $1 RETURNING to_jsonb(reputation_rules)",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(before) = before else {
+        return Err(Error::NotFound);
+    };
+
+    history::record(
+        &mut tx,
+        &actor,
+        RuleKind::ReputationRule,
+        &id.to_string(),
+        RuleOperation::Delete,
+        Some(before),
+        None,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_reputation_rules(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Reputation rule deleted successfully".to_string(),
+        data: None,
+    }))
+}
+
+async fn get_author_standing(
+    State(state): State<AppContext>,
+    Path(author_id): Path<String>,
+) -> Result<Json<ApiResponse<AuthorStanding>>, Error> {
+    let verdicts = state.cache.reputation.verdicts(&author_id).await;
+    let rules = state.cache.reputation_rules.read().unwrap().clone();
+    let flagged_by = reputation::tripped(&rules, &verdicts, chrono::Utc::now())
+        .into_iter()
+        .map(|(rule, _)| rule.id)
+        .collect();
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Author standing retrieved successfully".to_string(),
+        data: AuthorStanding {
+            author_id,
+            flagged_by,
+            verdicts,
+        },
+    }))
+}
+
+async fn reset_author_standing(
+    State(state): State<AppContext>,
+    Path(author_id): Path<String>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    state
+        .cache
+        .reputation
+        .reset(&state.pool, &author_id)
+        .await?;
+
+    info!("Author standing reset | Author: {}", author_id);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Author standing reset successfully".to_string(),
+        data: None,
+    }))
+}
+
+async fn list_reasons(
+    State(state): State<AppContext>,
+) -> Result<Json<ApiResponse<Vec<ReasonTemplateRow>>>, Error> {
+    let rows: Vec<ReasonTemplateRow> =
+        sqlx::query_as("SELECT * FROM reason_templates ORDER BY locale, reason_key")
+            .fetch_all(&state.pool)
+            .await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Reason templates retrieved successfully".to_string(),
+        data: rows,
+    }))
+}
+
+async fn upsert_reason(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Json(body): Json<ReasonTemplateInsert>,
+) -> Result<Json<ApiResponse<ReasonTemplateRow>>, Error> {
+    body.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let mut tx = state.pool.begin().await?;
+
+    let existing: Option<i32> =
+        sqlx::query_scalar("SELECT id FROM reason_templates WHERE locale = $1 AND reason_key = $2")
+            .bind(&body.locale)
+            .bind(body.reason_key)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+    let before = match existing {
+        Some(id) => snapshot(&mut tx, RuleKind::ReasonTemplate, &id.to_string()).await?,
+        None => None,
+    };
+
+    let row: ReasonTemplateRow = sqlx::query_as(
+        "INSERT INTO reason_templates (locale, reason_key, template) VALUES ($1, $2, $3)
+         ON CONFLICT (locale, reason_key) DO UPDATE
+         SET template = EXCLUDED.template,
+             version = reason_templates.version + 1
+         RETURNING *",
+    )
+    .bind(&body.locale)
+    .bind(body.reason_key)
+    .bind(&body.template)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let operation = match before {
+        Some(_) => RuleOperation::Update,
+        None => RuleOperation::Create,
+    };
+    history::record_change(
+        &mut tx,
+        &actor,
+        RuleKind::ReasonTemplate,
+        &row.id.to_string(),
+        operation,
+        before,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_reason_templates(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Reason template saved successfully".to_string(),
+        data: row,
+    }))
+}
+
+async fn delete_reason(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(id): Path<i32>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    let mut tx = state.pool.begin().await?;
+
+    let before: Option<serde_json::Value> = sqlx::query_scalar(
+        "DELETE FROM reason_templates WHERE id = $1 RETURNING to_jsonb(reason_templates)",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(before) = before else {
+        return Err(Error::NotFound);
+    };
+
+    history::record(
+        &mut tx,
+        &actor,
+        RuleKind::ReasonTemplate,
+        &id.to_string(),
+        RuleOperation::Delete,
+        Some(before),
+        None,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_reason_templates(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Reason template deleted successfully".to_string(),
+        data: None,
+    }))
+}
+
+async fn list_shadow_hits(
+    State(state): State<AppContext>,
+) -> Result<Json<ApiResponse<Vec<ShadowHit>>>, Error> {
+    let mut items: Vec<ShadowHit> = state
+        .cache
+        .shadow_hits
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(&(rule_kind, rule_id), &hits)| ShadowHit {
+            rule_kind,
+            rule_id,
+            hits,
+        })
+        .collect();
+    items.sort_by_key(|hit| std::cmp::Reverse(hit.hits));
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Shadow rule hits retrieved successfully".to_string(),
+        data: items,
+    }))
+}
+
+async fn list_history(
+    State(state): State<AppContext>,
+    Query(query): Query<RuleHistoryQuery>,
+) -> Result<Json<ApiResponse<Page<RuleHistoryRow>>>, Error> {
+    query
+        .validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM rule_history WHERE TRUE");
+
+    if let Some(cursor) = &query.cursor {
+        let before: i64 = cursor
+            .parse()
+            .map_err(|_| Error::Validation("invalid cursor".into()))?;
+        builder.push(" AND id < ").push_bind(before);
+    }
+    if let Some(kind) = query.kind {
+        builder.push(" AND rule_kind = ").push_bind(kind);
+    }
+    if let Some(rule_key) = &query.rule_key {
+        builder.push(" AND rule_key = ").push_bind(rule_key.clone());
+    }
+
+    // Newest first
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    builder
+        .push(" ORDER BY id DESC LIMIT ")
+        .push_bind(limit + 1);
+
+    let rows: Vec<RuleHistoryRow> = builder.build_query_as().fetch_all(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Rule history retrieved successfully".to_string(),
+        data: into_page(rows, limit, |r| r.id),
+    }))
+}
+
+/// Puts the rule back to the state recorded right after the given history entry
+async fn rollback_rule(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Path(id): Path<i64>,
+) -> Result<Json<ApiResponse<Option<String>>>, Error> {
+    let mut tx = state.pool.begin().await?;
+
+    let entry: Option<RuleHistoryRow> = sqlx::query_as("SELECT * FROM rule_history WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+    let Some(entry) = entry else {
+        return Err(Error::NotFound);
+    };
+
+    let before = snapshot(&mut tx, entry.rule_kind, &entry.rule_key).await?;
+    history::restore(
+        &mut tx,
+        entry.rule_kind,
+        &entry.rule_key,
+        entry.after.as_ref(),
+    )
+    .await?;
+    if matches!(
+        entry.rule_kind,
+        RuleKind::BadWord | RuleKind::Regex | RuleKind::DomainRule | RuleKind::Expression
+    ) {
+        expressions::check_references(&mut tx).await?;
+    }
+    history::record_change(
+        &mut tx,
+        &actor,
+        entry.rule_kind,
+        &entry.rule_key,
+        RuleOperation::Rollback,
+        before,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    match entry.rule_kind {
+        RuleKind::BadWord => state.cache.reload_bad_words(&state.pool).await?,
+        RuleKind::Regex => state.cache.reload_regex_rules(&state.pool).await?,
+        RuleKind::Setting => state.cache.reload_settings(&state.pool).await?,
+        RuleKind::AllowWord => state.cache.reload_allow_words(&state.pool).await?,
+        RuleKind::ReasonTemplate => state.cache.reload_reason_templates(&state.pool).await?,
+        // Categories aren't cached, their thresholds are kept in place by the restore
+        RuleKind::Category | RuleKind::CategoryThreshold => {
+            state.cache.reload_category_thresholds(&state.pool).await?
+        }
+        RuleKind::Expression => state.cache.reload_rule_expressions(&state.pool).await?,
+        RuleKind::PiiDetector => state.cache.reload_pii_detectors(&state.pool).await?,
+        RuleKind::DomainRule => state.cache.reload_domain_rules(&state.pool).await?,
+        RuleKind::ReputationRule => state.cache.reload_reputation_rules(&state.pool).await?,
+    }
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Rule rolled back successfully".to_string(),
+        data: None,
+    }))
+}
+
+/// Exports bad words, regex rules, settings, allow words, categories and category thresholds.
+/// Rule expressions, domain rules, PII detectors, reputation rules and reason templates are
+/// not part of the bundle, a replace import leaves them untouched.
+async fn export_rules(
+    State(state): State<AppContext>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, Error> {
+    let bad_words: Vec<BadWordRow> = sqlx::query_as("SELECT * FROM bad_words ORDER BY id")
+        .fetch_all(&state.pool)
+        .await?;
+    let regex_rules: Vec<RegexRuleRow> = sqlx::query_as("SELECT * FROM regex_rules ORDER BY id")
+        .fetch_all(&state.pool)
+        .await?;
+    let settings: Vec<SettingRow> = sqlx::query_as("SELECT * FROM settings ORDER BY key")
+        .fetch_all(&state.pool)
+        .await?;
+    let allow_words: Vec<AllowWordRow> = sqlx::query_as("SELECT * FROM allow_words ORDER BY id")
+        .fetch_all(&state.pool)
+        .await?;
+    let categories: Vec<CategoryRow> = sqlx::query_as("SELECT * FROM categories ORDER BY name")
+        .fetch_all(&state.pool)
+        .await?;
+    let category_thresholds: Vec<CategoryThresholdRow> =
+        sqlx::query_as("SELECT * FROM category_thresholds ORDER BY id")
+            .fetch_all(&state.pool)
+            .await?;
+
+    let bundle = RuleBundle {
+        version: RULE_BUNDLE_VERSION,
+        bad_words: bad_words
+            .into_iter()
+            .map(|r| BadWordCreate {
+                word: r.word,
+                action: r.moderation_action,
+                valid_from: r.valid_from,
+                valid_until: r.valid_until,
+                enabled: r.enabled,
+                mode: r.mode,
+                reason_code: r.reason_code,
+                category: r.category,
+                weight: r.weight,
+                language: r.language,
+            })
+            .collect(),
+        regex_rules: regex_rules
+            .into_iter()
+            .map(|r| RegexRuleCreate {
+                pattern: r.pattern,
+                description: r.description,
+                action: r.moderation_action,
+                valid_from: r.valid_from,
+                valid_until: r.valid_until,
+                enabled: r.enabled,
+                mode: r.mode,
+                reason_code: r.reason_code,
+                category: r.category,
+                weight: r.weight,
+                language: r.language,
+            })
+            .collect(),
+        settings: settings
+            .into_iter()
+            .map(|r| SettingInsert {
+                key: r.key,
+                value: r.value,
+            })
+            .collect(),
+        allow_words: allow_words
+            .into_iter()
+            .map(|r| AllowWordCreate { word: r.word })
+            .collect(),
+        categories: categories
+            .into_iter()
+            .map(|r| CategoryInsert {
+                name: r.name,
+                description: r.description,
+            })
+            .collect(),
+        category_thresholds: category_thresholds
+            .into_iter()
+            .map(|r| CategoryThresholdInsert {
+                category: r.category,
+                min_hits: r.min_hits,
+                action: r.moderation_action,
+            })
+            .collect(),
+    };
+
+    match query.format {
+        BundleFormat::Json => Ok((
+            [(
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"rules.json\"",
+            )],
+            Json(bundle),
+        )
+            .into_response()),
+        BundleFormat::Csv => {
+            let body = bundle_to_csv(bundle).map_err(|e| {
+                error!("Failed to serialize rule bundle as CSV: {}", e);
+                Error::Internal
+            })?;
+
+            Ok((
+                [
+                    (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        "attachment; filename=\"rules.csv\"",
+                    ),
+                ],
+                body,
+            )
+                .into_response())
+        }
+    }
+}
+
+/// Replace mode only clears the tables the export covers, see [`export_rules`]
+async fn import_rules(
+    State(state): State<AppContext>,
+    Extension(actor): Extension<ApiKeyId>,
+    Query(query): Query<ImportQuery>,
+    body: String,
+) -> Result<Json<ApiResponse<ImportSummary>>, Error> {
+    let bundle = match query.format {
+        BundleFormat::Json => {
+            serde_json::from_str(&body).map_err(|e| Error::Validation(e.to_string()))?
+        }
+        BundleFormat::Csv => bundle_from_csv(&body)?,
+    };
+
+    bundle
+        .validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    // Reject the whole bundle before touching the database if any pattern is broken
+    for rule in &bundle.regex_rules {
+        Regex::new(&rule.pattern).map_err(|e| Error::Regex(e.to_string()))?;
+    }
+
+    let mut tx = state.pool.begin().await?;
+
+    if let ImportMode::Replace = query.mode {
+        // Rules the bundle has again are upserted below and keep their ids, so expressions
+        // referencing them stay valid
+        let words: Vec<&str> = bundle.bad_words.iter().map(|w| w.word.as_str()).collect();
+        let languages: Vec<Option<&str>> = bundle
+            .bad_words
+            .iter()
+            .map(|w| w.language.as_deref())
+            .collect();
+        let deleted: Vec<(String, serde_json::Value)> = sqlx::query_as(
+            "DELETE FROM bad_words b
+             WHERE NOT EXISTS (
+                 SELECT 1 FROM unnest($1::text[], $2::text[]) AS kept (word, language)
+                 WHERE kept.word = b.word AND kept.language IS NOT DISTINCT FROM b.language
+             )
+             RETURNING id::text, to_jsonb(b)",
+        )
+        .bind(&words)
+        .bind(&languages)
+        .fetch_all(&mut *tx)
+        .await?;
+        for (key, before) in deleted {
+            history::record(
+                &mut tx,
+                &actor,
+                RuleKind::BadWord,
+                &key,
+                RuleOperation::Delete,
+                Some(before),
+                None,
+            )
+            .await?;
+        }
+
+        let patterns: Vec<&str> = bundle
+            .regex_rules
+            .iter()
+            .map(|r| r.pattern.as_str())
+            .collect();
+        let deleted: Vec<(String, serde_json::Value)> = sqlx::query_as(
+            "DELETE FROM regex_rules WHERE pattern <> ALL($1) RETURNING id::text, to_jsonb(regex_rules)",
+        )
+        .bind(&patterns)
+        .fetch_all(&mut *tx)
+        .await?;
+        for (key, before) in deleted {
+            history::record(
+                &mut tx,
+                &actor,
+                RuleKind::Regex,
+                &key,
+                RuleOperation::Delete,
+                Some(before),
+                None,
+            )
+            .await?;
+        }
+
+        let deleted: Vec<(String, serde_json::Value)> =
+            sqlx::query_as("DELETE FROM settings RETURNING key, to_jsonb(settings)")
+                .fetch_all(&mut *tx)
+                .await?;
+        for (key, before) in deleted {
+            history::record(
+                &mut tx,
+                &actor,
+                RuleKind::Setting,
+                &key,
+                RuleOperation::Delete,
+                Some(before),
+                None,
+            )
+            .await?;
+        }
+
+        let deleted: Vec<(String, serde_json::Value)> =
+            sqlx::query_as("DELETE FROM allow_words RETURNING id::text, to_jsonb(allow_words)")
+                .fetch_all(&mut *tx)
+                .await?;
+        for (key, before) in deleted {
+            history::record(
+                &mut tx,
+                &actor,
+                RuleKind::AllowWord,
+                &key,
+                RuleOperation::Delete,
+                Some(before),
+                None,
+            )
+            .await?;
+        }
+    }
+
+    // Kept in replace mode too, the rule tables default to the built-in categories
+    for category in &bundle.categories {
+        upsert_category(&mut tx, &actor, category).await?;
+    }
+
+    for threshold in &bundle.category_thresholds {
+        upsert_threshold(&mut tx, &actor, threshold).await?;
+    }
+
+    for word in &bundle.bad_words {
+        let existing: Option<i32> = sqlx::query_scalar(
+            "SELECT id FROM bad_words WHERE word = $1 AND language IS NOT DISTINCT FROM $2",
+        )
+        .bind(&word.word)
+        .bind(&word.language)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let before = match existing {
+            Some(id) => snapshot(&mut tx, RuleKind::BadWord, &id.to_string()).await?,
+            None => None,
+        };
+
+        let id: i32 = sqlx::query_scalar(
+            "INSERT INTO bad_words (word, moderation_action, valid_from, valid_until, enabled, mode, reason_code, category, weight, language)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+             ON CONFLICT (word, language) DO UPDATE
+             SET moderation_action = EXCLUDED.moderation_action,
+                 valid_from = EXCLUDED.valid_from,
+                 valid_until = EXCLUDED.valid_until,
+                 enabled = EXCLUDED.enabled,
+                 mode = EXCLUDED.mode,
+                 reason_code = EXCLUDED.reason_code,
+                 category = EXCLUDED.category,
+                 weight = EXCLUDED.weight,
+                 version = bad_words.version + 1
+             RETURNING id",
+        )
+        .bind(&word.word)
+        .bind(word.action)
+        .bind(word.valid_from)
+        .bind(word.valid_until)
+        .bind(word.enabled)
+        .bind(word.mode)
+        .bind(&word.reason_code)
+        .bind(&word.category)
+        .bind(word.weight)
+        .bind(&word.language)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let operation = match before {
+            Some(_) => RuleOperation::Update,
+            None => RuleOperation::Create,
+        };
+        history::record_change(
+            &mut tx,
+            &actor,
+            RuleKind::BadWord,
+            &id.to_string(),
+            operation,
+            before,
+        )
+        .await?;
+    }
+
+    // regex_rules has no unique constraint on pattern, so merge by pattern manually
+    for rule in &bundle.regex_rules {
+        let existing: Vec<i32> =
+            sqlx::query_scalar("SELECT id FROM regex_rules WHERE pattern = $1 ORDER BY id")
+                .bind(&rule.pattern)
+                .fetch_all(&mut *tx)
+                .await?;
+
+        for id in &existing {
+            let key = id.to_string();
+            let before = snapshot(&mut tx, RuleKind::Regex, &key).await?;
+
+            sqlx::query(
+                "UPDATE regex_rules
+                 SET description = $2, moderation_action = $3,
+                     valid_from = $4, valid_until = $5,
+                     enabled = $6, mode = $7,
+                     reason_code = $8, category = $9, weight = $10,
+                     language = $11,
+                     version = version + 1
+                 WHERE id = $1",
+            )
+            .bind(id)
+            .bind(&rule.description)
+            .bind(rule.action)
+            .bind(rule.valid_from)
+            .bind(rule.valid_until)
+            .bind(rule.enabled)
+            .bind(rule.mode)
+            .bind(&rule.reason_code)
+            .bind(&rule.category)
+            .bind(rule.weight)
+            .bind(&rule.language)
+            .execute(&mut *tx)
+            .await?;
+
+            history::record_change(
+                &mut tx,
+                &actor,
+                RuleKind::Regex,
+                &key,
+                RuleOperation::Update,
+                before,
+            )
+            .await?;
+        }
+
+        if existing.is_empty() {
+            let id: i32 = sqlx::query_scalar(
+                "INSERT INTO regex_rules (pattern, description, moderation_action, valid_from, valid_until, enabled, mode, reason_code, category, weight, language)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) RETURNING id",
+            )
+            .bind(&rule.pattern)
+            .bind(&rule.description)
+            .bind(rule.action)
+            .bind(rule.valid_from)
+            .bind(rule.valid_until)
+            .bind(rule.enabled)
+            .bind(rule.mode)
+            .bind(&rule.reason_code)
+            .bind(&rule.category)
+            .bind(rule.weight)
+            .bind(&rule.language)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            history::record_change(
+                &mut tx,
+                &actor,
+                RuleKind::Regex,
+                &id.to_string(),
+                RuleOperation::Create,
+                None,
+            )
+            .await?;
+        }
+    }
+
+    for setting in &bundle.settings {
+        upsert_setting(&mut tx, &actor, setting).await?;
+    }
+
+    for word in &bundle.allow_words {
+        insert_allow_word(&mut tx, &actor, word).await?;
+    }
+
+    // Replace mode must not drop a rule an expression still looks at
+    expressions::check_references(&mut tx).await?;
+
+    tx.commit().await?;
+
+    state.cache.reload_bad_words(&state.pool).await?;
+    state.cache.reload_regex_rules(&state.pool).await?;
+    state.cache.reload_settings(&state.pool).await?;
+    state.cache.reload_allow_words(&state.pool).await?;
+    state.cache.reload_category_thresholds(&state.pool).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Rules imported successfully".to_string(),
+        data: ImportSummary {
+            bad_words: bundle.bad_words.len(),
+            regex_rules: bundle.regex_rules.len(),
+            settings: bundle.settings.len(),
+            allow_words: bundle.allow_words.len(),
+            categories: bundle.categories.len(),
+            category_thresholds: bundle.category_thresholds.len(),
+        },
+    }))
+}
+
+const DEFAULT_PAGE_SIZE: i64 = 100;
+
+/// Adds the cursor, action, category and search conditions shared by the rule list endpoints
+fn push_list_filters(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    query: &RuleListQuery,
+    search_columns: &[&str],
+) -> Result<(), Error> {
+    if let Some(cursor) = &query.cursor {
+        let after: i32 = cursor
+            .parse()
+            .map_err(|_| Error::Validation("invalid cursor".into()))?;
+        builder.push(" AND id > ").push_bind(after);
+    }
+
+    if let Some(action) = query.action {
+        builder.push(" AND moderation_action = ").push_bind(action);
+    }
+
+    if let Some(category) = &query.category {
+        builder.push(" AND category = ").push_bind(category.clone());
+    }
+
+    if let Some(q) = &query.q {
+        let escaped = q
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let needle = format!("%{escaped}%");
+
+        builder.push(" AND (");
+        for (i, column) in search_columns.iter().enumerate() {
+            if i > 0 {
+                builder.push(" OR ");
+            }
+            builder
+                .push(format!("{column} ILIKE "))
+                .push_bind(needle.clone());
+        }
+        builder.push(")");
+    }
+
+    Ok(())
+}
+
+/// Orders by id and fetches one extra row to know whether another page exists
+fn push_page(builder: &mut QueryBuilder<'_, Postgres>, query: &RuleListQuery) -> i64 {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    builder.push(" ORDER BY id LIMIT ").push_bind(limit + 1);
+    limit
+}
+
+fn into_page<T, K: ToString>(mut rows: Vec<T>, limit: i64, id: impl Fn(&T) -> K) -> Page<T> {
+    let next_cursor = if rows.len() as i64 > limit {
+        rows.truncate(limit as usize);
+        rows.last().map(|r| id(r).to_string())
+    } else {
+        None
+    };
+
+    Page {
+        items: rows,
+        next_cursor,
+    }
+}
+
+/// Version the client based its edit on, `If-Match` wins over the body field
+fn expected_version(headers: &HeaderMap, body_version: Option<i32>) -> Result<i32, Error> {
+    if let Some(value) = headers.get(header::IF_MATCH) {
+        let raw = value
+            .to_str()
+            .map_err(|_| Error::Validation("invalid If-Match header".into()))?;
+
+        return raw
+            .trim()
+            .trim_start_matches("W/")
+            .trim_matches('"')
+            .parse()
+            .map_err(|_| Error::Validation("invalid If-Match header".into()));
+    }
+
+    body_version.ok_or_else(|| Error::Validation("If-Match header or version is required".into()))
+}
+
+fn with_etag<T: serde::Serialize>(version: i32, body: ApiResponse<T>) -> Response {
+    ([(header::ETAG, format!("\"{version}\""))], Json(body)).into_response()
+}
+
+fn canonical_domain(raw: &str) -> Result<String, Error> {
+    urls::canonical_domain(raw).ok_or_else(|| Error::Validation(format!("invalid domain: {raw}")))
+}
+
+fn unique_violation_as(err: sqlx::Error, message: &str) -> Error {
+    match &err {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            Error::Validation(message.to_string())
+        }
+        _ => Error::Db(err),
+    }
+}
+
+/// Inserts the word unless it already exists, only new rows end up in the history
+async fn insert_allow_word(
+    conn: &mut PgConnection,
+    actor: &ApiKeyId,
+    word: &AllowWordCreate,
+) -> Result<(), Error> {
+    let inserted: Option<i32> = sqlx::query_scalar(
+        "INSERT INTO allow_words (word) VALUES ($1) ON CONFLICT DO NOTHING RETURNING id",
+    )
+    .bind(&word.word)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(id) = inserted {
+        history::record_change(
+            conn,
+            actor,
+            RuleKind::AllowWord,
+            &id.to_string(),
+            RuleOperation::Create,
+            None,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn upsert_setting(
+    conn: &mut PgConnection,
+    actor: &ApiKeyId,
+    setting: &SettingInsert,
+) -> Result<(), Error> {
+    let before = snapshot(&mut *conn, RuleKind::Setting, &setting.key).await?;
+
+    sqlx::query(
+        "INSERT INTO settings (key, value) VALUES ($1, $2)
+         ON CONFLICT (key) DO UPDATE SET value = EXCLUDED.value",
+    )
+    .bind(&setting.key)
+    .bind(&setting.value)
+    .execute(&mut *conn)
+    .await?;
+
+    let operation = match before {
+        Some(_) => RuleOperation::Update,
+        None => RuleOperation::Create,
+    };
+    history::record_change(
+        conn,
+        actor,
+        RuleKind::Setting,
+        &setting.key,
+        operation,
+        before,
+    )
+    .await
+}
+
+async fn upsert_category(
+    conn: &mut PgConnection,
+    actor: &ApiKeyId,
+    category: &CategoryInsert,
+) -> Result<(), Error> {
+    let before = snapshot(&mut *conn, RuleKind::Category, &category.name).await?;
+
+    sqlx::query(
+        "INSERT INTO categories (name, description) VALUES ($1, $2)
+         ON CONFLICT (name) DO UPDATE SET description = EXCLUDED.description",
+    )
+    .bind(&category.name)
+    .bind(&category.description)
+    .execute(&mut *conn)
+    .await?;
+
+    let operation = match before {
+        Some(_) => RuleOperation::Update,
+        None => RuleOperation::Create,
+    };
+    history::record_change(
+        conn,
+        actor,
+        RuleKind::Category,
+        &category.name,
+        operation,
+        before,
+    )
+    .await
+}
+
+async fn upsert_threshold(
+    conn: &mut PgConnection,
+    actor: &ApiKeyId,
+    threshold: &CategoryThresholdInsert,
+) -> Result<(), Error> {
+    let existing: Option<i32> = sqlx::query_scalar(
+        "SELECT id FROM category_thresholds WHERE category = $1 AND min_hits = $2",
+    )
+    .bind(&threshold.category)
+    .bind(threshold.min_hits)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let before = match existing {
+        Some(id) => snapshot(&mut *conn, RuleKind::CategoryThreshold, &id.to_string()).await?,
+        None => None,
+    };
+
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO category_thresholds (category, min_hits, moderation_action) VALUES ($1, $2, $3)
+         ON CONFLICT (category, min_hits) DO UPDATE
+         SET moderation_action = EXCLUDED.moderation_action,
+             version = category_thresholds.version + 1
+         RETURNING id",
+    )
+    .bind(&threshold.category)
+    .bind(threshold.min_hits)
+    .bind(threshold.action)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let operation = match before {
+        Some(_) => RuleOperation::Update,
+        None => RuleOperation::Create,
+    };
+    history::record_change(
+        conn,
+        actor,
+        RuleKind::CategoryThreshold,
+        &id.to_string(),
+        operation,
+        before,
+    )
+    .await
+}
+
+fn bundle_to_csv(bundle: RuleBundle) -> Result<String, Box<dyn std::error::Error>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer.serialize(RuleBundleCsvRecord {
+        kind: "meta".into(),
+        key: Some("version".into()),
+        value: bundle.version.to_string(),
+        action: None,
+        description: None,
+        valid_from: None,
+        valid_until: None,
+        enabled: None,
+        mode: None,
+        reason_code: None,
+        category: None,
+        weight: None,
+        language: None,
+    })?;
+    for c in bundle.categories {
+        writer.serialize(RuleBundleCsvRecord {
+            kind: "category".into(),
+            key: None,
+            value: c.name,
+            action: None,
+            description: c.description,
+            valid_from: None,
+            valid_until: None,
+            enabled: None,
+            mode: None,
+            reason_code: None,
+            category: None,
+            weight: None,
+            language: None,
+        })?;
+    }
+    for t in bundle.category_thresholds {
+        writer.serialize(RuleBundleCsvRecord {
+            kind: "category_threshold".into(),
+            key: Some(t.category),
+            value: t.min_hits.to_string(),
+            action: Some(t.action),
+            description: None,
+            valid_from: None,
+            valid_until: None,
+            enabled: None,
+            mode: None,
+            reason_code: None,
+            category: None,
+            weight: None,
+            language: None,
+        })?;
+    }
+    for w in bundle.bad_words {
+        writer.serialize(RuleBundleCsvRecord {
+            kind: "bad_word".into(),
+            key: None,
+            value: w.word,
+            action: Some(w.action),
+            description: None,
+            valid_from: w.valid_from,
+            valid_until: w.valid_until,
+            enabled: Some(w.enabled),
+            mode: Some(w.mode),
+            reason_code: Some(w.reason_code),
+            category: Some(w.category),
+            weight: Some(w.weight),
+            language: w.language,
+        })?;
+    }
+    for r in bundle.regex_rules {
+        writer.serialize(RuleBundleCsvRecord {
+            kind: "regex".into(),
+            key: None,
+            value: r.pattern,
+            action: Some(r.action),
+            description: r.description,
+            valid_from: r.valid_from,
+            valid_until: r.valid_until,
+            enabled: Some(r.enabled),
+            mode: Some(r.mode),
+            reason_code: Some(r.reason_code),
+            category: Some(r.category),
+            weight: Some(r.weight),
+            language: r.language,
+        })?;
+    }
+    for w in bundle.allow_words {
+        writer.serialize(RuleBundleCsvRecord {
+            kind: "allow_word".into(),
+            key: None,
+            value: w.word,
+            action: None,
+            description: None,
+            valid_from: None,
+            valid_until: None,
+            enabled: None,
+            mode: None,
+            reason_code: None,
+            category: None,
+            weight: None,
+            language: None,
+        })?;
+    }
+    for s in bundle.settings {
+        writer.serialize(RuleBundleCsvRecord {
+            kind: "setting".into(),
+            key: Some(s.key),
+            value: s.value,
+            action: None,
+            description: None,
+            valid_from: None,
+            valid_until: None,
+            enabled: None,
+            mode: None,
+            reason_code: None,
+            category: None,
+            weight: None,
+            language: None,
+        })?;
+    }
+
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+fn bundle_from_csv(body: &str) -> Result<RuleBundle, Error> {
+    let mut reader = csv::Reader::from_reader(body.as_bytes());
+    let mut bundle = RuleBundle {
+        version: 0,
+        bad_words: Vec::new(),
+        regex_rules: Vec::new(),
+        settings: Vec::new(),
+        allow_words: Vec::new(),
+        categories: Vec::new(),
+        category_thresholds: Vec::new(),
+    };
+
+    let headers = reader
+        .headers()
+        .map_err(|e| Error::Validation(e.to_string()))?
+        .clone();
+    for record in reader.records() {
+        let record = record.map_err(|e| Error::Validation(e.to_string()))?;
+        // Quoted values can span lines, so the record index isn't the line
+        let line = record.position().map_or(0, |p| p.line());
+        let record: RuleBundleCsvRecord = record
+            .deserialize(Some(&headers))
+            .map_err(|e| Error::Validation(format!("line {line}: {e}")))?;
+        let missing = |field: &str| Error::Validation(format!("line {line}: missing {field}"));
+
+        match record.kind.as_str() {
+            "meta" if record.key.as_deref() == Some("version") => {
+                bundle.version = record
+                    .value
+                    .parse()
+                    .map_err(|_| Error::Validation(format!("line {line}: invalid version")))?;
+            }
+            "bad_word" => bundle.bad_words.push(BadWordCreate {
+                word: record.value,
+                action: record.action.ok_or_else(|| missing("action"))?,
+                valid_from: record.valid_from,
+                valid_until: record.valid_until,
+                enabled: record.enabled.unwrap_or_else(enabled_by_default),
+                mode: record.mode.unwrap_or_default(),
+                reason_code: record.reason_code.unwrap_or_else(default_bad_word_code),
+                category: record.category.unwrap_or_else(default_bad_word_category),
+                weight: record.weight.unwrap_or_default(),
+                language: record.language,
+            }),
+            "regex" => bundle.regex_rules.push(RegexRuleCreate {
+                pattern: record.value,
+                description: record.description,
+                action: record.action.ok_or_else(|| missing("action"))?,
+                valid_from: record.valid_from,
+                valid_until: record.valid_until,
+                enabled: record.enabled.unwrap_or_else(enabled_by_default),
+                mode: record.mode.unwrap_or_default(),
+                reason_code: record.reason_code.unwrap_or_else(default_regex_code),
+                category: record.category.unwrap_or_else(default_regex_category),
+                weight: record.weight.unwrap_or_default(),
+                language: record.language,
+            }),
+            "category" => bundle.categories.push(CategoryInsert {
+                name: record.value,
+                description: record.description,
+            }),
+            "category_threshold" => bundle.category_thresholds.push(CategoryThresholdInsert {
+                category: record.key.ok_or_else(|| missing("key"))?,
+                min_hits: record
+                    .value
+                    .parse()
+                    .map_err(|_| Error::Validation(format!("line {line}: invalid min_hits")))?,
+                action: record.action.ok_or_else(|| missing("action"))?,
+            }),
+            "allow_word" => bundle
+                .allow_words
+                .push(AllowWordCreate { word: record.value }),
+            "setting" => bundle.settings.push(SettingInsert {
+                key: record.key.ok_or_else(|| missing("key"))?,
+                value: record.value,
+            }),
+            other => {
+                return Err(Error::Validation(format!(
+                    "line {line}: unknown record kind '{other}'"
+                )))
+            }
+        }
+    }
+
+    Ok(bundle)
 }
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::{cache::ModerationCache, errors::Error};
+
+/// Upper bound for a single sleep, also covers clock drift and changes made directly in the database
+const MAX_IDLE: Duration = Duration::from_secs(300);
+/// Lower bound for a single sleep, a window reached just now mustn't turn into a busy loop
+const MIN_IDLE: Duration = Duration::from_secs(1);
+/// Back off after a failed reload instead of spinning on a broken database
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Rebuilds the matchers whenever a rule's validity window opens or closes
+pub async fn run(pool: PgPool, cache: ModerationCache) {
+    loop {
+        let wait = match next_transition(&pool).await {
+            Ok(Some((at, now))) => (at - now)
+                .to_std()
+                .unwrap_or_default()
+                .clamp(MIN_IDLE, MAX_IDLE),
+            Ok(None) => MAX_IDLE,
+            Err(e) => {
+                error!("Failed to read the next rule transition: {}", e);
+                RETRY_DELAY
+            }
+        };
+
+        debug!("Rule scheduler sleeping for {:?}", wait);
+
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            // A rule was added or edited, its window might open sooner than the one we wait for
+            _ = cache.rules_changed.notified() => continue,
+        }
+
+        info!("Rule validity window reached, reloading matchers");
+
+        if let Err(e) = cache.reload_bad_words(&pool).await {
+            error!("Scheduled bad words reload failed: {}", e);
+        }
+        if let Err(e) = cache.reload_regex_rules(&pool).await {
+            error!("Scheduled regex rules reload failed: {}", e);
+        }
+    }
+}
+
+/// Next window start or end along with the database's clock, the matchers are reloaded
+/// against that clock so the sleep is measured on it too
+async fn next_transition(pool: &PgPool) -> Result<Option<(DateTime<Utc>, DateTime<Utc>)>, Error> {
+    let (at, now): (Option<DateTime<Utc>>, DateTime<Utc>) = sqlx::query_as(
+        "SELECT MIN(at), now() FROM (
+             SELECT valid_from AS at FROM bad_words WHERE valid_from > now()
+             UNION ALL SELECT valid_until FROM bad_words WHERE valid_until > now()
+             UNION ALL SELECT valid_from FROM regex_rules WHERE valid_from > now()
+             UNION ALL SELECT valid_until FROM regex_rules WHERE valid_until > now()
+         ) transitions",
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(at.map(|at| (at, now)))
+}
@@ -0,0 +1,138 @@
+use regex::Regex;
+
+lazy_static::lazy_static! {
+    /// Links with an optional scheme and path. Dots may be spaced out or obfuscated as `[.]`,
+    /// `(.)`, `{.}`, `[dot]` or `(dot)`. Punycode TLDs come first, `xn` alone would end the
+    /// match at the dash.
+    static ref LINK: Regex = Regex::new(
+        r"(?:(?:https?|hxxps?)://)?(?P<host>(?:[\p{L}\p{N}](?:[\p{L}\p{N}-]{0,61}[\p{L}\p{N}])?\s?(?:\.|[\[({]\s?(?:\.|dot)\s?[\])}])\s?)+(?:xn--[a-z0-9-]{1,59}|\p{L}{2,63}))\b(?:[/?#]\S*)?"
+    )
+    .unwrap();
+    static ref SEPARATOR: Regex = Regex::new(r"\s?(?:\.|[\[({]\s?(?:\.|dot)\s?[\])}])\s?").unwrap();
+}
+
+/// Link found in lowercased text
+pub struct Link {
+    /// Byte span of the whole link, scheme and path included
+    pub start: usize,
+    pub end: usize,
+    /// Canonical host, see [`canonical_domain`]
+    pub host: String,
+}
+
+pub fn extract(text: &str) -> Vec<Link> {
+    LINK.captures_iter(text)
+        .filter_map(|caps| {
+            let link = caps.get(0)?;
+            let host = canonical_domain(&caps["host"])?;
+            Some(Link {
+                start: link.start(),
+                end: link.end(),
+                host,
+            })
+        })
+        .collect()
+}
+
+/// Lowercased punycode form of a domain with obfuscated dots restored, so `Örnek[.]com`,
+/// `örnek.com` and `xn--rnek-4qa.com` all end up the same. A leading `*.` is dropped.
+pub fn canonical_domain(raw: &str) -> Option<String> {
+    let dotted = SEPARATOR.replace_all(raw.trim(), ".");
+    let host = dotted.trim_start_matches("*.").trim_end_matches('.');
+
+    let ascii = idna::domain_to_ascii(host).ok()?;
+    let valid = !ascii.is_empty()
+        && ascii.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+
+    valid.then_some(ascii)
+}
+
+/// The domain itself followed by every parent domain, e.g. `a.b.com`, `b.com`, `com`
+pub fn suffixes(domain: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(domain), |d| {
+        d.split_once('.').map(|(_, parent)| parent)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hosts(text: &str) -> Vec<String> {
+        extract(text).into_iter().map(|link| link.host).collect()
+    }
+
+    #[test]
+    fn plain_links() {
+        assert_eq!(hosts("bak: https://example.com/a?b=1 ok"), ["example.com"]);
+        assert_eq!(hosts("www.example.co.uk adresine"), ["www.example.co.uk"]);
+        assert!(hosts("no links here, 3.14 either").is_empty());
+    }
+
+    #[test]
+    fn obfuscated_dots() {
+        for text in [
+            "example[.]com",
+            "example(.)com",
+            "example{.}com",
+            "example[dot]com",
+            "example (dot) com",
+            "example [ . ] com",
+            "example . com",
+            "hxxps://example[.]com/path",
+        ] {
+            assert_eq!(hosts(text), ["example.com"], "{text}");
+        }
+        assert_eq!(
+            hosts("evil[.]sub(dot)example[.]org"),
+            ["evil.sub.example.org"]
+        );
+    }
+
+    #[test]
+    fn span_covers_the_whole_link() {
+        let text = "see hxxp://example[.]com/x now";
+        let links = extract(text);
+
+        assert_eq!(links.len(), 1);
+        assert_eq!(
+            &text[links[0].start..links[0].end],
+            "hxxp://example[.]com/x"
+        );
+    }
+
+    #[test]
+    fn unicode_domains_match_their_punycode_form() {
+        assert_eq!(canonical_domain("örnek.com").unwrap(), "xn--rnek-4qa.com");
+        assert_eq!(canonical_domain("Örnek[.]com").unwrap(), "xn--rnek-4qa.com");
+        assert_eq!(
+            canonical_domain("xn--rnek-4qa.com").unwrap(),
+            "xn--rnek-4qa.com"
+        );
+        assert_eq!(hosts("git örnek[.]com"), ["xn--rnek-4qa.com"]);
+        assert_eq!(hosts("git xn--rnek-4qa.com"), ["xn--rnek-4qa.com"]);
+        // Punycode TLD
+        assert_eq!(hosts("пример.рф"), ["xn--e1afmkfd.xn--p1ai"]);
+        assert_eq!(hosts("xn--e1afmkfd.xn--p1ai"), ["xn--e1afmkfd.xn--p1ai"]);
+    }
+
+    #[test]
+    fn canonical_domain_cleanup() {
+        assert_eq!(canonical_domain("*.Example.com.").unwrap(), "example.com");
+        assert_eq!(canonical_domain(""), None);
+        assert_eq!(canonical_domain("exa mple.com"), None);
+        assert_eq!(canonical_domain("a..com"), None);
+    }
+
+    #[test]
+    fn suffixes_walk_up_to_the_tld() {
+        assert_eq!(
+            suffixes("a.b.example.com").collect::<Vec<_>>(),
+            ["a.b.example.com", "b.example.com", "example.com", "com"]
+        );
+    }
+}
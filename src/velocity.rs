@@ -0,0 +1,54 @@
+use moka::future::Cache;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Upper bound for the `velocity_*_window_secs` settings
+pub const MAX_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+/// Counts saturate here, far above any sensible limit
+const MAX_EVENTS_PER_KEY: usize = 10_000;
+const MAX_KEYS: u64 = 1_000_000;
+
+/// Sliding window request log per (tenant, key), e.g. per author or per thread of an API key
+#[derive(Clone)]
+pub struct Velocity {
+    keys: Cache<(String, String), Arc<Mutex<VecDeque<Instant>>>>,
+}
+
+impl Velocity {
+    pub fn new() -> Self {
+        Self {
+            keys: Cache::builder()
+                .max_capacity(MAX_KEYS)
+                .time_to_idle(MAX_WINDOW)
+                .build(),
+        }
+    }
+
+    /// Records a request for the tenant's `key` and returns how many it had in the last
+    /// `window`, this one included
+    pub async fn record(&self, tenant: &str, key: &str, window: Duration) -> usize {
+        let events = self
+            .keys
+            .get_with((tenant.to_string(), key.to_string()), async {
+                Default::default()
+            })
+            .await;
+        let mut events = events.lock().unwrap();
+
+        let now = Instant::now();
+        while events
+            .front()
+            .is_some_and(|&at| now.duration_since(at) > window.min(MAX_WINDOW))
+        {
+            events.pop_front();
+        }
+
+        if events.len() == MAX_EVENTS_PER_KEY {
+            events.pop_front();
+        }
+        events.push_back(now);
+
+        events.len()
+    }
+}